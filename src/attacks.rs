@@ -0,0 +1,117 @@
+use crate::bitboard::{self, Bitboard, square_bit, squares};
+use crate::state::State;
+use crate::types::{Color, PieceKind, Square};
+
+impl State {
+    /* Every piece of color that attacks square, given where all the pieces stand now */
+    pub fn attackers_to(&self, square: Square, color: Color) -> Bitboard {
+        let occupied = self.occupied();
+        let queens = self.pieces(color, PieceKind::Queen);
+
+        // a pawn attacks square if a pawn of the other colour on square would attack it back
+        return (bitboard::pawn_attacks(color.opponent(), square) & self.pieces(color, PieceKind::Pawn)) |
+            (bitboard::knight_attacks(square) & self.pieces(color, PieceKind::Knight)) |
+            (bitboard::king_attacks(square) & self.pieces(color, PieceKind::King)) |
+            (bitboard::rook_attacks(square, occupied) & (self.pieces(color, PieceKind::Rook) | queens)) |
+            (bitboard::bishop_attacks(square, occupied) & (self.pieces(color, PieceKind::Bishop) | queens));
+    }
+
+    /* Whether any piece of color attacks square */
+    pub fn is_attacked(&self, square: Square, color: Color) -> bool {
+        return self.attackers_to(square, color) != 0;
+    }
+
+    /* Whether color's king is attacked */
+    pub fn is_in_check(&self, color: Color) -> bool {
+        return self.is_attacked(self.king_square(color), color.opponent());
+    }
+
+    /* The opposing pieces giving check to the side to move. Two of them means only a king move can help */
+    pub fn checkers(&self) -> Bitboard {
+        return self.attackers_to(self.king_square(self.turn), self.turn.opponent());
+    }
+
+    /*
+    color's pieces that are pinned to their own king. Each stands alone between the king and an opposing
+    rook, bishop or queen on the same line, so moving off that line would expose the king
+    */
+    pub fn pinned(&self, color: Color) -> Bitboard {
+        let king = self.king_square(color);
+        let opp = color.opponent();
+        let queens = self.pieces(opp, PieceKind::Queen);
+
+        // sliders that would attack the king if nothing were in the way
+        let snipers = (bitboard::rook_attacks(king, 0) & (self.pieces(opp, PieceKind::Rook) | queens)) |
+            (bitboard::bishop_attacks(king, 0) & (self.pieces(opp, PieceKind::Bishop) | queens));
+
+        let mut pinned: Bitboard = 0;
+        for sniper in squares(snipers) {
+            let blockers = bitboard::between(king, sniper) & self.occupied();
+            if blockers.count_ones() == 1 && blockers & self.occupancy(color) != 0 {
+                pinned |= blockers;
+            }
+        }
+        return pinned;
+    }
+
+    /* Whether color's piece on square is pinned to its king */
+    pub fn is_pinned(&self, square: Square, color: Color) -> bool {
+        return self.pinned(color) & square_bit(square) != 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(name: &str) -> Square {
+        return Square::from_name(name).unwrap();
+    }
+
+    fn position(fen: &str) -> State {
+        return State::from_fen(fen).unwrap();
+    }
+
+    #[test]
+    fn attackers_include_pawns_and_sliders() {
+        let state = position("4k3/8/8/8/7b/4p3/8/4K3 w - - 0 1");
+        let attackers = state.attackers_to(square("f2"), Color::Black);
+        assert_eq!(attackers, square_bit(square("e3")) | square_bit(square("h4")));
+        assert!(!state.is_attacked(square("e2"), Color::Black));
+    }
+
+    #[test]
+    fn single_checker() {
+        let state = position("4k3/8/8/8/8/5n2/8/4K3 w - - 0 1");
+        assert!(state.in_check());
+        assert_eq!(state.checkers(), square_bit(square("f3")));
+    }
+
+    #[test]
+    fn double_check() {
+        let state = position("4r1k1/8/8/8/8/5n2/8/4K3 w - - 0 1");
+        assert_eq!(state.checkers(), square_bit(square("e8")) | square_bit(square("f3")));
+        // only the king can move
+        assert!(state.generate_legal_moves().iter().all(|action| action.from == square("e1")));
+    }
+
+    #[test]
+    fn piece_pinned_to_its_king() {
+        let state = position("4r1k1/8/8/8/8/8/4N3/4K3 w - - 0 1");
+        assert_eq!(state.pinned(Color::White), square_bit(square("e2")));
+        assert!(state.is_pinned(square("e2"), Color::White));
+        assert!(!state.is_in_check(Color::White));
+    }
+
+    #[test]
+    fn x_rayed_pieces_are_not_pinned() {
+        // two pieces between the rook and king, so neither is pinned and the king isn't attacked
+        let state = position("4r1k1/8/8/8/8/4B3/4N3/4K3 w - - 0 1");
+        assert_eq!(state.pinned(Color::White), 0);
+        assert_eq!(state.attackers_to(square("e1"), Color::Black), 0);
+
+        // an opposing piece in the way doesn't make a pin either
+        let state = position("4r1k1/8/8/8/8/4b3/4N3/4K3 w - - 0 1");
+        assert_eq!(state.pinned(Color::White), 0);
+    }
+}
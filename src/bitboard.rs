@@ -0,0 +1,150 @@
+use crate::magic;
+use crate::types::{Color, Square};
+
+/*
+A set of squares packed into 64 bits, one bit per square. Bit 0 is a1 and bit 63 is h8, matching
+Square's numbering
+*/
+pub type Bitboard = u64;
+
+// Every square on the a file
+pub const FILE_A: Bitboard = 0x0101_0101_0101_0101;
+
+// Squares a knight on each square can jump to
+pub const KNIGHT_ATTACKS: [Bitboard; 64] = leaper_table(&[(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)]);
+
+// Squares a king on each square can step to
+pub const KING_ATTACKS: [Bitboard; 64] = leaper_table(&[(0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1)]);
+
+// Squares a pawn of each colour attacks from each square, indexed by Color::index
+pub const PAWN_ATTACKS: [[Bitboard; 64]; 2] = [leaper_table(&[(-1, 1), (1, 1)]), leaper_table(&[(-1, -1), (1, -1)])];
+
+/* Builds the table of squares reachable from each square by a single (file, rank) step in steps */
+const fn leaper_table(steps: &[(i8, i8)]) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    let mut index = 0;
+    while index < 64 {
+        let file = (index % 8) as i8;
+        let rank = (index / 8) as i8;
+        let mut i = 0;
+        while i < steps.len() {
+            let target_file = file + steps[i].0;
+            let target_rank = rank + steps[i].1;
+            if target_file >= 0 && target_file < 8 && target_rank >= 0 && target_rank < 8 {
+                table[index] |= 1 << (target_rank * 8 + target_file);
+            }
+            i += 1;
+        }
+        index += 1;
+    }
+    return table;
+}
+
+/* The bitboard with only square set */
+pub fn square_bit(square: Square) -> Bitboard {
+    return 1 << square.index();
+}
+
+/* The squares attacked by a pawn of color standing on square */
+pub fn pawn_attacks(color: Color, square: Square) -> Bitboard {
+    return PAWN_ATTACKS[color.index()][square.index() as usize];
+}
+
+/* The squares a knight on square attacks */
+pub fn knight_attacks(square: Square) -> Bitboard {
+    return KNIGHT_ATTACKS[square.index() as usize];
+}
+
+/* The squares a king on square attacks */
+pub fn king_attacks(square: Square) -> Bitboard {
+    return KING_ATTACKS[square.index() as usize];
+}
+
+/* Moves every square in board one rank towards the opponent of color */
+pub fn push(board: Bitboard, color: Color) -> Bitboard {
+    return match color {
+        Color::White => board << 8,
+        Color::Black => board >> 8,
+    };
+}
+
+/* Iterates over the squares set in a bitboard, from a1 upwards */
+pub struct Squares(Bitboard);
+
+impl Iterator for Squares {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        if self.0 == 0 {
+            return None;
+        }
+        let square = Square::from_index(self.0.trailing_zeros() as u8);
+        // clear the lowest set bit
+        self.0 &= self.0 - 1;
+        return Some(square);
+    }
+}
+
+/* The squares set in board */
+pub fn squares(board: Bitboard) -> Squares {
+    return Squares(board);
+}
+
+// Straight line directions as (file, rank) steps: up, right, down, left
+pub(crate) const STRAIGHT_DIRECTIONS: [(i8, i8); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+
+// Diagonal directions as (file, rank) steps: up-right, down-right, down-left, up-left
+pub(crate) const DIAGONAL_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+/*
+Squares reached sliding from square in each of directions, up to and including the first occupied
+square. Whether that square can be captured is left to the caller. This walks the board a square at a
+time, so it's only used to build the magic tables
+*/
+pub(crate) fn sliding_attacks(square: Square, directions: &[(i8, i8)], occupied: Bitboard) -> Bitboard {
+    let mut attacks: Bitboard = 0;
+    for &(file_step, rank_step) in directions {
+        let mut current = square;
+        while let Some(target) = current.offset(file_step, rank_step) {
+            attacks |= square_bit(target);
+            if occupied & square_bit(target) != 0 {
+                break;
+            }
+            current = target;
+        }
+    }
+    return attacks;
+}
+
+/* The squares a rook on square attacks, given every occupied square on the board */
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    return magic::rook_attacks(square, occupied);
+}
+
+/* The squares a bishop on square attacks, given every occupied square on the board */
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    return magic::bishop_attacks(square, occupied);
+}
+
+/* The squares a queen on square attacks, the rook and bishop attacks together */
+pub fn queen_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    return rook_attacks(square, occupied) | bishop_attacks(square, occupied);
+}
+
+/*
+The squares strictly between a and b, if they share a rank, file or diagonal. Empty if they don't, or if
+they're next to each other
+*/
+pub fn between(a: Square, b: Square) -> Bitboard {
+    let file_gap = a.file().abs_diff(b.file());
+    let rank_gap = a.rank().abs_diff(b.rank());
+
+    // slide from each end towards the other, the squares both reach are the ones in between
+    if a.file() == b.file() || a.rank() == b.rank() {
+        return rook_attacks(a, square_bit(b)) & rook_attacks(b, square_bit(a));
+    }
+    if file_gap == rank_gap {
+        return bishop_attacks(a, square_bit(b)) & bishop_attacks(b, square_bit(a));
+    }
+    return 0;
+}
@@ -0,0 +1,278 @@
+use std::fmt;
+use crate::state::{CastlingRights, State};
+use crate::types::{Color, Piece, PieceKind, Square};
+
+/* The FEN string for the normal starting position */
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/* Reasons a FEN string can fail to parse */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum FenError {
+    // FEN needs 6 space separated fields. The move counters can be left off, so 4 is accepted too
+    WrongFieldCount(usize),
+    // The piece placement field needs 8 ranks separated by '/'
+    WrongRankCount(usize),
+    // A rank has to describe exactly 8 squares
+    BadRankLength { rank: u8, squares: usize },
+    InvalidPiece(char),
+    // Each side needs exactly one king
+    KingCount { side: Color, count: usize },
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    // Not a square, or not one a pawn of the side that just moved can have skipped
+    InvalidEnPassant(String),
+    // The side that just moved left its own king in check, so the other side could take it
+    OpponentInCheck(Color),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            FenError::WrongFieldCount(count) => write!(f, "expected 6 fields separated by spaces, found {}", count),
+            FenError::WrongRankCount(count) => write!(f, "expected 8 ranks separated by '/', found {}", count),
+            FenError::BadRankLength { rank, squares } => write!(f, "rank {} describes {} squares instead of 8", rank, squares),
+            FenError::InvalidPiece(piece) => write!(f, "'{}' is not a piece", piece),
+            FenError::KingCount { side, count } => write!(f, "{} has {} kings, needs exactly 1", side, count),
+            FenError::InvalidSideToMove(field) => write!(f, "side to move should be 'w' or 'b', found '{}'", field),
+            FenError::InvalidCastling(field) => write!(f, "castling rights should be '-' or some of 'KQkq', found '{}'", field),
+            FenError::InvalidEnPassant(field) => {
+                write!(f, "en passant square should be '-' or the square a pawn that just moved two skipped, found '{}'", field)
+            },
+            FenError::OpponentInCheck(side) => write!(f, "{} is in check but it isn't their move", side),
+            FenError::InvalidHalfmoveClock(field) => write!(f, "halfmove clock should be a number, found '{}'", field),
+            FenError::InvalidFullmoveNumber(field) => write!(f, "fullmove number should be a number above 0, found '{}'", field),
+        };
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl State {
+    /*
+    Create a state from a FEN string. Covers piece placement, side to move, castling rights, the en passant
+    square and both move counters
+    */
+    pub fn from_fen(fen: &str) -> Result<State, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 6 && fields.len() != 4 {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        }
+
+        let mut state: State = Default::default();
+
+        // piece placement, starting from the 8th rank
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::WrongRankCount(ranks.len()));
+        }
+        for (i, rank_text) in ranks.iter().enumerate() {
+            let rank = 7 - i as u8;
+            let mut file: usize = 0;
+            for letter in rank_text.chars() {
+                // digits are runs of empty squares
+                if let Some(empty) = letter.to_digit(10) {
+                    file += empty as usize;
+                    continue;
+                }
+                let piece = Piece::from_letter(letter).ok_or(FenError::InvalidPiece(letter))?;
+                if file < 8 {
+                    state.put_piece(Square::new(file as u8, rank), piece);
+                }
+                file += 1;
+            }
+            if file != 8 {
+                return Err(FenError::BadRankLength { rank: rank + 1, squares: file });
+            }
+        }
+
+        let white_kings = state.pieces(Color::White, PieceKind::King).count_ones() as usize;
+        if white_kings != 1 {
+            return Err(FenError::KingCount { side: Color::White, count: white_kings });
+        }
+        let black_kings = state.pieces(Color::Black, PieceKind::King).count_ones() as usize;
+        if black_kings != 1 {
+            return Err(FenError::KingCount { side: Color::Black, count: black_kings });
+        }
+
+        state.turn = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            _ => { return Err(FenError::InvalidSideToMove(fields[1].to_string())); }
+        };
+
+        if fields[2] != "-" {
+            for letter in fields[2].chars() {
+                match letter {
+                    'K' => { state.castling.white_kingside = true },
+                    'Q' => { state.castling.white_queenside = true },
+                    'k' => { state.castling.black_kingside = true },
+                    'q' => { state.castling.black_queenside = true },
+                    _ => { return Err(FenError::InvalidCastling(fields[2].to_string())); }
+                }
+            }
+        }
+        // a right is only kept if the king and that rook are still on their starting squares, otherwise the
+        // same position could be written with different rights and get a different key
+        let has = |color: Color, kind: PieceKind, file: u8| {
+            state.piece_at(Square::new(file, color.home_rank())) == Some(Piece::new(kind, color))
+        };
+        let possible = CastlingRights {
+            white_kingside: has(Color::White, PieceKind::King, 4) && has(Color::White, PieceKind::Rook, 7),
+            white_queenside: has(Color::White, PieceKind::King, 4) && has(Color::White, PieceKind::Rook, 0),
+            black_kingside: has(Color::Black, PieceKind::King, 4) && has(Color::Black, PieceKind::Rook, 7),
+            black_queenside: has(Color::Black, PieceKind::King, 4) && has(Color::Black, PieceKind::Rook, 0),
+        };
+        state.castling.white_kingside &= possible.white_kingside;
+        state.castling.white_queenside &= possible.white_queenside;
+        state.castling.black_kingside &= possible.black_kingside;
+        state.castling.black_queenside &= possible.black_queenside;
+
+        if fields[3] != "-" {
+            let square = Square::from_name(fields[3]);
+            // the skipped square is behind the opponent's pawn that just moved two, so on the 6th rank when white
+            // is to move and the 3rd when black is
+            let mover = state.turn.opponent();
+            let valid = square.is_some_and(|square| {
+                let skipped_rank = (mover.home_rank() as i8 + 2 * mover.forward()) as u8;
+                let pawn = square.offset(0, mover.forward());
+                square.rank() == skipped_rank && state.piece_at(square).is_none() &&
+                pawn.is_some_and(|pawn| state.piece_at(pawn) == Some(Piece::new(PieceKind::Pawn, mover)))
+            });
+            if !valid {
+                return Err(FenError::InvalidEnPassant(fields[3].to_string()));
+            }
+            state.en_passant = square;
+        }
+
+        if state.is_in_check(state.turn.opponent()) {
+            return Err(FenError::OpponentInCheck(state.turn.opponent()));
+        }
+
+        state.fullmove_number = 1;
+        if fields.len() == 6 {
+            state.halfmove_clock = fields[4].parse()
+                .map_err(|_| FenError::InvalidHalfmoveClock(fields[4].to_string()))?;
+            state.fullmove_number = match fields[5].parse() {
+                Ok(number) if number > 0 => number,
+                _ => { return Err(FenError::InvalidFullmoveNumber(fields[5].to_string())); }
+            };
+        }
+
+        state.key = state.compute_key();
+        state.history.push(state.key);
+        return Ok(state);
+    }
+
+    /* Generate the FEN string describing this state */
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+            for file in 0..8 {
+                match self.piece_at(Square::new(file, rank)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece.letter());
+                    }
+                    None => { empty += 1 }
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(match self.turn {
+            Color::White => " w ",
+            Color::Black => " b ",
+        });
+
+        let rights: CastlingRights = self.castling;
+        let mut castling = String::new();
+        for (allowed, letter) in [(rights.white_kingside, 'K'), (rights.white_queenside, 'Q'),
+        (rights.black_kingside, 'k'), (rights.black_queenside, 'q')] {
+            if allowed {
+                castling.push(letter);
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+        fen.push_str(&castling);
+
+        let en_passant = match self.en_passant {
+            Some(square) => square.to_string(),
+            None => String::from("-"),
+        };
+        fen.push_str(&format!(" {} {} {}", en_passant, self.halfmove_clock, self.fullmove_number));
+
+        return fen;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_to_fen() {
+        for fen in [START_FEN,
+                    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                    "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+                    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 12 40"] {
+            assert_eq!(State::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn drops_castling_rights_without_king_and_rook() {
+        let state = State::from_fen("4k3/8/8/8/8/8/8/6K1 w KQkq - 0 1").unwrap();
+        assert_eq!(state.to_fen(), "4k3/8/8/8/8/8/8/6K1 w - - 0 1");
+        assert_eq!(state.zobrist_key(), State::from_fen("4k3/8/8/8/8/8/8/6K1 w - - 0 1").unwrap().zobrist_key());
+
+        // only the rights whose rook is missing go
+        let state = State::from_fen("r3k3/8/8/8/8/8/8/4K2R w KQkq - 0 1").unwrap();
+        assert_eq!(state.to_fen(), "r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1");
+    }
+
+    #[test]
+    fn rejects_side_not_to_move_in_check() {
+        let result = State::from_fen("4k3/8/8/8/8/8/8/4RK2 w - - 0 1");
+        assert_eq!(result.err(), Some(FenError::OpponentInCheck(Color::Black)));
+
+        // the side to move being in check is normal
+        assert!(State::from_fen("4k3/8/8/8/8/8/8/4RK2 b - - 0 1").is_ok());
+    }
+
+    #[test]
+    fn en_passant_square_must_follow_a_double_push() {
+        // rank 3 is only possible with black to move
+        let result = State::from_fen("4k3/8/8/8/8/8/3PP3/4K3 w - e3 0 1");
+        assert_eq!(result.err(), Some(FenError::InvalidEnPassant(String::from("e3"))));
+
+        // no pawn in front of the square
+        let result = State::from_fen("4k3/8/8/8/8/8/3P4/4K3 b - e3 0 1");
+        assert_eq!(result.err(), Some(FenError::InvalidEnPassant(String::from("e3"))));
+
+        let state = State::from_fen("4k3/8/8/8/4P3/8/3P4/4K3 b - e3 0 1").unwrap();
+        assert_eq!(state.en_passant_square(), Square::from_name("e3"));
+    }
+
+    #[test]
+    fn rejects_malformed_fields() {
+        assert_eq!(State::from_fen("8/8/8 w - -").err(), Some(FenError::WrongRankCount(3)));
+        assert_eq!(State::from_fen("4k3/8/8/8/8/8/8/8 w - - 0 1").err(),
+            Some(FenError::KingCount { side: Color::White, count: 0 }));
+        assert_eq!(State::from_fen("4k3/8/8/8/8/8/8/4K3 x - - 0 1").err(),
+            Some(FenError::InvalidSideToMove(String::from("x"))));
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt;
+use crate::types::Color;
+
+/* Ways a game can end in a draw without a stalemate */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DrawReason {
+    ThreefoldRepetition,
+    FiftyMoveRule,
+    InsufficientMaterial,
+}
+
+impl fmt::Display for DrawReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            DrawReason::ThreefoldRepetition => "threefold repetition",
+            DrawReason::FiftyMoveRule => "fifty-move rule",
+            DrawReason::InsufficientMaterial => "insufficient material",
+        };
+        return write!(f, "{}", reason);
+    }
+}
+
+/*
+Where a game stands. Everything other than Ongoing means the game is over. Checkmate, stalemate and draws
+are worked out from the board, resignation and loss on time have to be recorded on the state by whoever
+is running the game
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate { winner: Color },
+    Stalemate,
+    Draw(DrawReason),
+    Resignation { winner: Color },
+    Timeout { winner: Color },
+}
+
+impl GameStatus {
+    /* Returns whether the game has finished */
+    pub fn is_over(&self) -> bool {
+        return *self != GameStatus::Ongoing;
+    }
+
+    /* Returns the side that won, or None if the game is drawn or still going */
+    pub fn winner(&self) -> Option<Color> {
+        return match self {
+            GameStatus::Checkmate { winner } |
+            GameStatus::Resignation { winner } |
+            GameStatus::Timeout { winner } => Some(*winner),
+            _ => None,
+        };
+    }
+
+    /* Result in the usual score notation: "1-0", "0-1", "1/2-1/2", or "*" while the game is going */
+    pub fn result(&self) -> &'static str {
+        return match (self, self.winner()) {
+            (GameStatus::Ongoing, _) => "*",
+            (_, Some(Color::White)) => "1-0",
+            (_, Some(Color::Black)) => "0-1",
+            (_, None) => "1/2-1/2",
+        };
+    }
+}
+
+impl fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            GameStatus::Ongoing => write!(f, "The game is still going."),
+            GameStatus::Checkmate { winner } => write!(f, "Checkmate. {} wins.", winner),
+            GameStatus::Stalemate => write!(f, "Stalemate. The game is a draw."),
+            GameStatus::Draw(reason) => write!(f, "The game is a draw by {}.", reason),
+            GameStatus::Resignation { winner } => write!(f, "{} resigns. {} wins.", winner.opponent(), winner),
+            GameStatus::Timeout { winner } => write!(f, "{} ran out of time. {} wins.", winner.opponent(), winner),
+        };
+    }
+}
@@ -0,0 +1,281 @@
+use std::fmt;
+use crate::bitboard::{self, Bitboard, square_bit, squares};
+use crate::state::State;
+use crate::types::{Color, Move, Piece, PieceKind, Square};
+
+/* Why a move can't be played, so the player can be told what they got wrong */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IllegalMove {
+    // There's nothing on the starting square
+    NoPiece { square: Square },
+    // The piece on the starting square belongs to the other side
+    OpponentPiece { square: Square, piece: Piece },
+    // The end square holds one of the player's own pieces
+    CaptureOwnPiece { square: Square },
+    // The piece doesn't move in that pattern, like a bishop moving straight
+    CantMoveThatWay { kind: PieceKind, from: Square, to: Square },
+    // A pawn moving diagonally onto an empty square
+    NothingToCapture { square: Square },
+    // Another piece stands in the way
+    PathBlocked { kind: PieceKind, blocker: Square },
+    // Promoting when the move doesn't reach the last rank, or to a pawn or king
+    InvalidPromotion,
+    // A pawn reaching the last rank without saying what it becomes
+    MissingPromotion,
+    // The king or that side's rook has already moved, or the rook has been taken
+    CastlingRightsLost { kingside: bool },
+    CastleOutOfCheck,
+    // A square the king passes over on its way is attacked
+    CastleThroughCheck { square: Square },
+    // The king would be attacked on the square it moves to
+    KingIntoCheck { square: Square },
+    // The piece is the only thing between its king and an opposing rook, bishop or queen
+    Pinned { kind: PieceKind, square: Square, pinner: PieceKind, pinner_square: Square },
+    // The king is in check and the move doesn't deal with it
+    StillInCheck,
+    // Any other way of leaving the king attacked, like an en passant capture clearing a rank
+    ExposesKing,
+}
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            IllegalMove::NoPiece { square } => write!(f, "There is no piece on {}", square),
+            IllegalMove::OpponentPiece { square, piece } => {
+                write!(f, "The {} on {} belongs to {}", piece.kind, square, piece.color)
+            },
+            IllegalMove::CaptureOwnPiece { square } => write!(f, "You can't capture your own piece on {}", square),
+            IllegalMove::CantMoveThatWay { kind, from, to } => write!(f, "A {} can't move from {} to {}", kind, from, to),
+            IllegalMove::NothingToCapture { square } => {
+                write!(f, "Pawns only move diagonally to capture, and there is nothing to take on {}", square)
+            },
+            IllegalMove::PathBlocked { kind, blocker } => write!(f, "The {}'s path is blocked by the piece on {}", kind, blocker),
+            IllegalMove::InvalidPromotion => {
+                write!(f, "Only a pawn reaching the last rank can promote, to a queen, rook, bishop or knight")
+            },
+            IllegalMove::MissingPromotion => write!(f, "A pawn reaching the last rank has to promote, add =Q, =R, =B or =N"),
+            IllegalMove::CastlingRightsLost { kingside } => {
+                let side = if *kingside { "king" } else { "queen" };
+                write!(f, "You can no longer castle on the {} side, the king or that rook has already moved", side)
+            },
+            IllegalMove::CastleOutOfCheck => write!(f, "You can't castle while in check"),
+            IllegalMove::CastleThroughCheck { square } => write!(f, "The king can't castle through {}, it's attacked", square),
+            IllegalMove::KingIntoCheck { square } => write!(f, "The king can't move to {}, it would be in check there", square),
+            IllegalMove::Pinned { kind, square, pinner, pinner_square } => {
+                write!(f, "The {} on {} is pinned to your king by the {} on {}", kind, square, pinner, pinner_square)
+            },
+            IllegalMove::StillInCheck => write!(f, "Your king is in check, and that move doesn't get it out"),
+            IllegalMove::ExposesKing => write!(f, "That move would leave your king in check"),
+        };
+    }
+}
+
+impl std::error::Error for IllegalMove {}
+
+impl State {
+    /*
+    Work out why action can't be played by the side to move. Returns Ok if it's legal. The reasons are
+    checked roughly in the order a player would think about them, so the first problem found is reported
+    */
+    pub fn diagnose_move(&self, action: &Move) -> Result<(), IllegalMove> {
+        let from = action.from;
+        let to = action.to;
+
+        let piece = self.piece_at(from).ok_or(IllegalMove::NoPiece { square: from })?;
+        if piece.color != self.turn {
+            return Err(IllegalMove::OpponentPiece { square: from, piece });
+        }
+        if self.generate_legal_moves().contains(action) {
+            return Ok(());
+        }
+
+        // a king moving two squares along its home rank is trying to castle
+        if piece.kind == PieceKind::King && from == Square::new(4, self.turn.home_rank()) &&
+        to.rank() == from.rank() && from.file().abs_diff(to.file()) == 2 {
+            return Err(self.diagnose_castle(to.file() > from.file()));
+        }
+
+        if self.occupancy(self.turn) & square_bit(to) != 0 {
+            return Err(IllegalMove::CaptureOwnPiece { square: to });
+        }
+
+        self.diagnose_pattern(piece.kind, action)?;
+
+        let last_rank = piece.kind == PieceKind::Pawn && to.rank() == self.turn.opponent().home_rank();
+        let promotes_to_piece = action.promotion.is_some_and(|kind| PieceKind::PROMOTIONS.contains(&kind));
+        if last_rank && action.promotion.is_none() {
+            return Err(IllegalMove::MissingPromotion);
+        }
+        if last_rank != promotes_to_piece {
+            return Err(IllegalMove::InvalidPromotion);
+        }
+
+        // the move is fine on its own, so it must leave the king attacked
+        if piece.kind == PieceKind::King {
+            return Err(IllegalMove::KingIntoCheck { square: to });
+        }
+        if let Some(pinner_square) = self.pinner(from) {
+            // moving along the pin line is allowed, so this only counts when the move leaves it
+            let line = bitboard::between(self.king_square(self.turn), pinner_square) | square_bit(pinner_square);
+            if line & square_bit(to) == 0 {
+                let pinner = self.piece_at(pinner_square).expect("pinning piece is on the board").kind;
+                return Err(IllegalMove::Pinned { kind: piece.kind, square: from, pinner, pinner_square });
+            }
+        }
+        if self.in_check() {
+            return Err(IllegalMove::StillInCheck);
+        }
+        return Err(IllegalMove::ExposesKing);
+    }
+
+    /*
+    Why castling towards the king side or queen side isn't legal. Lost rights are checked first, so this also
+    works once the king has left its starting square
+    */
+    pub(crate) fn diagnose_castle(&self, kingside: bool) -> IllegalMove {
+        let rank = self.turn.home_rank();
+        let (kingside_right, queenside_right) = self.castling.for_side(self.turn);
+        let rook_file = if kingside { 7 } else { 0 };
+        let has_rook = self.pieces(self.turn, PieceKind::Rook) & square_bit(Square::new(rook_file, rank)) != 0;
+        if !(if kingside { kingside_right } else { queenside_right }) || !has_rook {
+            return IllegalMove::CastlingRightsLost { kingside };
+        }
+
+        // every square between the king and rook must be empty
+        let path = bitboard::between(Square::new(4, rank), Square::new(rook_file, rank));
+        if let Some(blocker) = squares(path & self.occupied()).next() {
+            return IllegalMove::PathBlocked { kind: PieceKind::King, blocker };
+        }
+
+        if self.in_check() {
+            return IllegalMove::CastleOutOfCheck;
+        }
+        let (passing, landing) = if kingside { (5, 6) } else { (3, 2) };
+        if self.is_attacked(Square::new(passing, rank), self.turn.opponent()) {
+            return IllegalMove::CastleThroughCheck { square: Square::new(passing, rank) };
+        }
+        return IllegalMove::KingIntoCheck { square: Square::new(landing, rank) };
+    }
+
+    /* Check action follows the way a piece of kind moves, and nothing is in its way */
+    fn diagnose_pattern(&self, kind: PieceKind, action: &Move) -> Result<(), IllegalMove> {
+        let from = action.from;
+        let to = action.to;
+        let cant_move = IllegalMove::CantMoveThatWay { kind, from, to };
+
+        let reach: Bitboard = match kind {
+            PieceKind::Pawn => { return self.diagnose_pawn(action); },
+            PieceKind::Knight => bitboard::knight_attacks(from),
+            PieceKind::King => bitboard::king_attacks(from),
+            PieceKind::Bishop => bitboard::bishop_attacks(from, 0),
+            PieceKind::Rook => bitboard::rook_attacks(from, 0),
+            PieceKind::Queen => bitboard::queen_attacks(from, 0),
+        };
+        if reach & square_bit(to) == 0 {
+            return Err(cant_move);
+        }
+
+        // report the blocker closest to the moving piece
+        let blockers = bitboard::between(from, to) & self.occupied();
+        let nearest = squares(blockers).min_by_key(|blocker| bitboard::between(from, *blocker).count_ones());
+        if let Some(blocker) = nearest {
+            return Err(IllegalMove::PathBlocked { kind, blocker });
+        }
+        return Ok(());
+    }
+
+    /* Same as diagnose_pattern, for pawns, which move differently when capturing */
+    fn diagnose_pawn(&self, action: &Move) -> Result<(), IllegalMove> {
+        let from = action.from;
+        let to = action.to;
+        let cant_move = IllegalMove::CantMoveThatWay { kind: PieceKind::Pawn, from, to };
+        let forward = self.turn.forward();
+        let rank_step = to.rank() as i8 - from.rank() as i8;
+
+        // diagonal captures, including en passant
+        if bitboard::pawn_attacks(self.turn, from) & square_bit(to) != 0 {
+            if self.occupancy(self.turn.opponent()) & square_bit(to) == 0 && self.en_passant != Some(to) {
+                return Err(IllegalMove::NothingToCapture { square: to });
+            }
+            return Ok(());
+        }
+
+        // straight ahead, one square or two from the starting rank
+        let start_rank = (self.turn.home_rank() as i8 + forward) as u8;
+        let straight = from.file() == to.file() &&
+            (rank_step == forward || (rank_step == 2 * forward && from.rank() == start_rank));
+        if !straight {
+            return Err(cant_move);
+        }
+        let path = bitboard::between(from, to) | square_bit(to);
+        if let Some(blocker) = squares(path & self.occupied()).min_by_key(|blocker| blocker.rank().abs_diff(from.rank())) {
+            return Err(IllegalMove::PathBlocked { kind: PieceKind::Pawn, blocker });
+        }
+        return Ok(());
+    }
+
+    /* The opposing piece pinning the side to move's piece on square to its king, if there is one */
+    fn pinner(&self, square: Square) -> Option<Square> {
+        if self.pinned(self.turn) & square_bit(square) == 0 {
+            return None;
+        }
+        let king = self.king_square(self.turn);
+        let opp: Color = self.turn.opponent();
+        return squares(self.occupancy(opp)).find(|pinner| {
+            let piece = self.piece_at(*pinner).expect("occupied square has a piece");
+            let slides = match piece.kind {
+                PieceKind::Rook => bitboard::rook_attacks(*pinner, 0),
+                PieceKind::Bishop => bitboard::bishop_attacks(*pinner, 0),
+                PieceKind::Queen => bitboard::queen_attacks(*pinner, 0),
+                _ => 0,
+            };
+            slides & square_bit(king) != 0 && bitboard::between(king, *pinner) & self.occupied() == square_bit(square)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnose(fen: &str, action: &str) -> Result<(), IllegalMove> {
+        let state = State::from_fen(fen).unwrap();
+        let from = Square::from_name(&action[0..2]).unwrap();
+        let to = Square::from_name(&action[2..4]).unwrap();
+        return state.diagnose_move(&Move::new(from, to));
+    }
+
+    fn square(name: &str) -> Square {
+        return Square::from_name(name).unwrap();
+    }
+
+    #[test]
+    fn pinned_piece_names_the_pinner() {
+        let result = diagnose("4r1k1/8/8/8/8/8/4N3/4K3 w - - 0 1", "e2c3");
+        assert_eq!(result, Err(IllegalMove::Pinned {
+            kind: PieceKind::Knight, square: square("e2"), pinner: PieceKind::Rook, pinner_square: square("e8"),
+        }));
+    }
+
+    #[test]
+    fn blocked_path_names_the_nearest_blocker() {
+        let result = diagnose(crate::fen::START_FEN, "a1a5");
+        assert_eq!(result, Err(IllegalMove::PathBlocked { kind: PieceKind::Rook, blocker: square("a2") }));
+    }
+
+    #[test]
+    fn castling_through_an_attacked_square() {
+        let result = diagnose("4kr2/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1");
+        assert_eq!(result, Err(IllegalMove::CastleThroughCheck { square: square("f1") }));
+    }
+
+    #[test]
+    fn castling_rights_lost() {
+        let result = diagnose("4k3/8/8/8/8/8/8/R3K2R w Q - 0 1", "e1g1");
+        assert_eq!(result, Err(IllegalMove::CastlingRightsLost { kingside: true }));
+
+        // the king has already left e1, and another piece has taken its place
+        let state = State::from_fen("6k1/8/8/8/8/8/8/R3RK2 w - - 0 1").unwrap();
+        assert_eq!(state.diagnose_castle(false), IllegalMove::CastlingRightsLost { kingside: false });
+    }
+}
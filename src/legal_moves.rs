@@ -1,300 +1,348 @@
-use std::collections::HashSet;
-use std::collections::HashMap;
+use crate::bitboard::{self, Bitboard, square_bit, squares};
+use crate::types::{Color, Move, PieceKind, Square};
+
+
 /*
-Check if coordinates would put piece out of bounds.
-- true: if in bounds
-- false: if out of bounds 
+Compute legal moves a pawn piece on square can do. Adds them to the passed in legal_moves vector.
+own and opp are the squares holding the current and opposing player's pieces. en_passant is the square
+skipped by an opposing pawn's double move last turn, which can be captured onto
 */
-pub fn in_bound(x:i8, y:i8) -> bool {
-    return (x >= 0 && x <= 7) && (y >= 0 && y <= 7);
-}
+pub fn pawn_legal_moves(color: Color, square: Square, own: Bitboard, opp: Bitboard, en_passant: Option<Square>,
+legal_moves:&mut Vec<Move>) {
 
+    let empty: Bitboard = !(own | opp);
 
-/* 
-Compute legal moves a pawn piece at loc_x,loc_y can do. Adds them to the passed in legal_moves vector
-*/
-pub fn pawn_legal_moves(white_turn: bool, loc_x:i8, loc_y:i8, cur_player:&HashMap<(i8, i8), char>,
-opp_player:&HashMap<(i8,i8), char>, legal_moves:&mut HashSet<[i8; 4]>) {
-
-    // white pawns move up(-), black pawns move down(+)
-    let direction: i8 = match white_turn {
-        true => {-1 }
-        false => { 1 }
-    };
-
-    // check up/down 
-    if in_bound(loc_x, loc_y+direction) && 
-    !cur_player.contains_key(&(loc_x, loc_y+direction)) && 
-    !opp_player.contains_key(&(loc_x, loc_y+direction)) {
-        let new_y = loc_y+direction;
-        //legal_moves.insert(format!("{loc_x},{loc_y} to {loc_x},{new_y}"));
-        legal_moves.insert([loc_x, loc_y, loc_x, new_y]);
-    }
-
-    // if first move, can move up/down twice
-    if (white_turn && loc_y == 6) || (!white_turn && loc_y == 1) &&
-    !cur_player.contains_key(&(loc_x, loc_y+direction*2)) && 
-    !opp_player.contains_key(&(loc_x, loc_y+direction*2)) {
-        let new_y = loc_y+direction*2;
-        //legal_moves.insert(format!("{loc_x},{loc_y} to {loc_x},{new_y}"));
-        legal_moves.insert([loc_x, loc_y, loc_x, new_y]);
-    }
-
-    // check diagonals for opposite team pieces
-    // up/down-left
-    if opp_player.contains_key(&(loc_x-1, loc_y+direction)) {
-        let new_x = loc_x-1;
-        let new_y = loc_y+direction;
-        //legal_moves.insert(format!("{loc_x},{loc_y} to {new_x},{new_y}"));
-        legal_moves.insert([loc_x, loc_y, new_x, new_y]);
-    }
-    // up/down-right
-    if opp_player.contains_key(&(loc_x+1, loc_y+direction)) {
-        let new_x = loc_x+1;
-        let new_y = loc_y+direction;
-        //legal_moves.insert(format!("{loc_x},{loc_y} to {new_x},{new_y}"));
-        legal_moves.insert([loc_x, loc_y, new_x, new_y]);
+    // check up/down. White pawns move up the ranks, black pawns move down
+    let one_step = bitboard::push(square_bit(square), color) & empty;
+    let mut targets = one_step;
+
+    // if first move, can move up/down twice. Both the square passed over and the landing square must be empty
+    let start_rank = (color.home_rank() as i8 + color.forward()) as u8;
+    if square.rank() == start_rank {
+        targets |= bitboard::push(one_step, color) & empty;
+    }
+
+    // check diagonals for opposite team pieces. En passant captures land on an empty square, but the pawn
+    // beside this one can still be taken
+    let mut capturable = opp;
+    if let Some(target) = en_passant {
+        capturable |= square_bit(target);
+    }
+    targets |= bitboard::pawn_attacks(color, square) & capturable;
+
+    for target in squares(targets) {
+        insert_pawn_move(color, square, target, legal_moves);
     }
 }
 
 
-/* 
-Compute legal moves a rook piece at loc_x,loc_y can do. Adds them to the passed in legal_moves vector
+/*
+Adds a pawn move to legal_moves. A pawn reaching the last rank has to promote, so one move is added
+for each piece it can become
 */
-pub fn rook_legal_moves(loc_x:i8, loc_y:i8, cur_player:&HashMap<(i8, i8), char>, opp_player:&HashMap<(i8,i8), char>, legal_moves:&mut HashSet<[i8; 4]>) {
-
-    for i in 0..4 {
-        let mut new_x: i8 = loc_x;
-        let mut new_y: i8 = loc_y;
-
-        let x_dir = match i {
-            // up/down
-            0 | 2 => 0,
-            // right
-            1 => 1,
-            //left
-            3 => -1,
-            _ => 0 //impossible to reach
-        };
-
-        let y_dir = match i {
-            // up
-            0 => -1,
-            // left/right
-            1 | 3 => 0,
-            // down
-            2 => 1,
-            _ => 0 //impossible to reach
-        };
-
-        // check each space from the current direction
-        loop {
-            new_x += x_dir;
-            new_y += y_dir;
-            if in_bound(new_x, new_y) &&
-            !cur_player.contains_key(&(new_x, new_y)) {
-                //legal_moves.insert(format!("{loc_x},{loc_y} to {new_x},{new_y}"));
-                legal_moves.insert([loc_x, loc_y, new_x, new_y]);
-
-                // if an opposing player's piece is on this coordinate, we must stop here
-                if opp_player.contains_key(&(new_x, new_y)) { break; }
-            }
-            // no more valid moves possible
-            else {
-                break;
-            }
+fn insert_pawn_move(color: Color, from: Square, to: Square, legal_moves:&mut Vec<Move>) {
+    if to.rank() == color.opponent().home_rank() {
+        for kind in PieceKind::PROMOTIONS {
+            legal_moves.push(Move::promote(from, to, kind));
         }
     }
+    else {
+        legal_moves.push(Move::new(from, to));
+    }
 }
 
 
-
-pub fn bishop_legal_moves(loc_x:i8, loc_y:i8, cur_player:&HashMap<(i8, i8), char>, opp_player:&HashMap<(i8,i8), char>, legal_moves:&mut HashSet<[i8; 4]>) {
-
-    // check the four diagonals. The changes to a coordinate for a given direction are decided by x_dir and y_dir
-    for i in 0..4 { 
-        let mut new_x: i8 = loc_x;
-        let mut new_y: i8 = loc_y;
-
-        let x_dir = match i {
-            //left
-            0 | 3 => -1,
-            //right
-            1 | 2 => 1,
-            _ => 0 //impossible to reach
-        };
-
-        let y_dir = match i {
-            //down
-            0 | 2 => 1,
-            //up
-            1 | 3 => -1,
-            _ => 0 //impossible to reach
-        };
-
-        // go through all spaces in the current diagonal until you can't anymore (piece or edge of board)
-        loop {
-            new_x += x_dir;
-            new_y += y_dir;
-
-            if in_bound(new_x, new_y) &&
-            !cur_player.contains_key(&(new_x, new_y)) {
-                //legal_moves.insert(format!("{loc_x},{loc_y} to {new_x},{new_y}"));
-                legal_moves.insert([loc_x, loc_y, new_x, new_y]);
-
-                // if an opposing player's piece is on this coordinate, we must stop here
-                if opp_player.contains_key(&(new_x, new_y)) { break; }
-            }
-            // no more valid moves possible
-            else {
-                break;
-            }
-        }
+/* Adds a move from square to each of the target squares */
+fn insert_moves(square: Square, targets: Bitboard, legal_moves:&mut Vec<Move>) {
+    for target in squares(targets) {
+        legal_moves.push(Move::new(square, target));
     }
 }
 
 
-pub fn queen_legal_moves(loc_x:i8, loc_y:i8, cur_player:&HashMap<(i8, i8), char>, opp_player:&HashMap<(i8,i8), char>, legal_moves:&mut HashSet<[i8; 4]>) {
-    // check paths from all 8 directions
-    for i in 0..8 {
-        let mut new_x = loc_x;
-        let mut new_y = loc_y;
-
-        // direction from origin. Goes clockwise 0-7
-        let x_dir = match i {
-            // up/down
-            0 | 4 => 0,
-            // upright, right, downright
-            1 | 2 | 3 => 1,
-            // downleft, left, upleft
-            5 | 6 | 7 => -1,
-            //should never be reached
-            _ => 0
-        };
-
-        let y_dir = match i {
-            // right/left
-            2 | 6 => 0,
-            // up, upright, up-left
-            0 | 1 | 7 => -1,
-            // downright, down, downleft
-            3 | 4 | 5 => 1,
-            //should never be reached
-            _ => 0
-        };
-        // go through all spaces in the current direction until you can't anymore (piece or edge of board)
-        loop {
-            new_x += x_dir;
-            new_y += y_dir;
-
-            if in_bound(new_x, new_y) &&
-            !cur_player.contains_key(&(new_x, new_y)) {
-                //legal_moves.insert(format!("{loc_x},{loc_y} to {new_x},{new_y}"));
-                legal_moves.insert([loc_x, loc_y, new_x, new_y]);
-                // if an opposing player's piece is on this coordinate, we must stop here
-                if opp_player.contains_key(&(new_x, new_y)) { break; }
-            }
-            // no more valid moves possible
-            else {
-                break;
-            }
-        }
-    }
+/*
+Compute legal moves a rook piece on square can do. Adds them to the passed in legal_moves vector.
+Opposing pieces can be captured, so the square they're on is included
+*/
+pub fn rook_legal_moves(square: Square, own: Bitboard, opp: Bitboard, legal_moves:&mut Vec<Move>) {
+    insert_moves(square, bitboard::rook_attacks(square, own | opp) & !own, legal_moves);
+}
+
+
+/*
+Compute legal moves a bishop piece on square can do, along the four diagonals
+*/
+pub fn bishop_legal_moves(square: Square, own: Bitboard, opp: Bitboard, legal_moves:&mut Vec<Move>) {
+    insert_moves(square, bitboard::bishop_attacks(square, own | opp) & !own, legal_moves);
+}
+
+
+/*
+Compute legal moves a queen piece on square can do, checking paths from all 8 directions
+*/
+pub fn queen_legal_moves(square: Square, own: Bitboard, opp: Bitboard, legal_moves:&mut Vec<Move>) {
+    insert_moves(square, bitboard::queen_attacks(square, own | opp) & !own, legal_moves);
 }
 
 
 /*
 Computes legal moves for king to take in the current state. Similar to queen function, but without looping - 1 move each direction
 */
-pub fn king_legal_moves(loc_x:i8, loc_y:i8, cur_player:&HashMap<(i8, i8), char>, legal_moves:&mut HashSet<[i8; 4]>) {
-    // check paths from all 8 directions
-    for i in 0..8 {
-        let new_x = loc_x;
-        let new_y = loc_y;
-
-        // direction from origin. Goes clockwise 0-7
-        let x_dir = match i {
-            // up/down
-            0 | 4 => 0,
-            // upright, right, downright
-            1 | 2 | 3 => 1,
-            // downleft, left, upleft
-            5 | 6 | 7 => -1,
-            //should never be reached
-            _ => 0
-        };
-
-        let y_dir = match i {
-            // right/left
-            2 | 6 => 0,
-            // up, upright, up-left
-            0 | 1 | 7 => -1,
-            // downright, down, downleft
-            3 | 4 | 5 => 1,
-            //should never be reached
-            _ => 0
-        };
-
-        // check if 1 space forward from current position is valid (in bound, no pieces from own side)
-        let new_x = new_x + x_dir;
-        let new_y = new_y + y_dir;
-
-        if in_bound(new_x, new_y) &&
-        !cur_player.contains_key(&(new_x, new_y)) {
-            //legal_moves.insert(format!("{loc_x},{loc_y} to {new_x},{new_y}"));
-            legal_moves.insert([loc_x, loc_y, new_x, new_y]);
-        }
+pub fn king_legal_moves(square: Square, own: Bitboard, legal_moves:&mut Vec<Move>) {
+    insert_moves(square, bitboard::king_attacks(square) & !own, legal_moves);
+}
+
+/*
+Computes legal moves knight can take in this current state
+*/
+pub fn knight_legal_moves(square: Square, own: Bitboard, legal_moves:&mut Vec<Move>) {
+    insert_moves(square, bitboard::knight_attacks(square) & !own, legal_moves);
+}
+
+
+/*
+Check if square is attacked by any of attacker's pieces, which belong to attacker_color. attacker holds
+the squares of each kind of piece, indexed by PieceKind::index. occupied is every piece on the board,
+so that sliding pieces are blocked correctly
+*/
+pub fn square_attacked(square: Square, attacker_color: Color, attacker: &[Bitboard; 6], occupied: Bitboard) -> bool {
+
+    // pawns attack diagonally forwards, so a pawn attacks square if a defending pawn on square would attack it
+    if bitboard::pawn_attacks(attacker_color.opponent(), square) & attacker[PieceKind::Pawn.index()] != 0 {
+        return true;
+    }
+    if bitboard::knight_attacks(square) & attacker[PieceKind::Knight.index()] != 0 {
+        return true;
+    }
+    if bitboard::king_attacks(square) & attacker[PieceKind::King.index()] != 0 {
+        return true;
+    }
 
+    // sliding pieces. Straight lines can be attacked by rooks and queens, diagonals by bishops and queens
+    let queens = attacker[PieceKind::Queen.index()];
+    if bitboard::rook_attacks(square, occupied) & (attacker[PieceKind::Rook.index()] | queens) != 0 {
+        return true;
     }
+    return bitboard::bishop_attacks(square, occupied) & (attacker[PieceKind::Bishop.index()] | queens) != 0;
 }
 
+
 /*
-Computes legal moves knight can take in this current state
+Computes castling moves for the current player. A castle is encoded as the king moving two spaces towards
+the rook. The king must not be in check, and must not pass through or land on an attacked square
 */
-pub fn knight_legal_moves(loc_x:i8, loc_y:i8, cur_player:&HashMap<(i8, i8), char>, legal_moves:&mut HashSet<[i8; 4]>) {
-
-    // the knight has 8 different possible landing points. This match looks confusing
-    // but trust me it gets them all. Combination of two spaces then one
-
-    for i in 0..8 {
-        let new_x = loc_x;
-        let new_y = loc_y;
-
-        // direction from origin. Goes clockwise 0-7
-        let x_dir = match i {
-            // short end goes left
-            0 | 5 => -1,
-            // short end goes right
-            1 | 4  => 1,
-            //long end goes left
-            6 | 7 => -2,
-            //long end goes right
-            2 | 3 => 2,
-            //should never be reached
-            _ => 0
-        };
-
-        let y_dir = match i {
-            // long end goes up
-            0 | 1 => -2,
-            // long end goes down
-            4 | 5 => 2,
-            // short end goes up
-            2 | 7 => -1,
-            // short end goes down
-            3 | 6 => 1,
-            //should never be reached
-            _ => 0
-        };
-
-        // check if landing position is valid (in bound, no pieces from own side)
-        let new_x = new_x + x_dir;
-        let new_y = new_y + y_dir;
-
-        if in_bound(new_x, new_y) &&
-        !cur_player.contains_key(&(new_x, new_y)) {
-            //legal_moves.insert(format!("{loc_x},{loc_y} to {new_x},{new_y}"));
-            legal_moves.insert([loc_x, loc_y, new_x, new_y]);
-        }
+pub fn castling_legal_moves(color: Color, kingside: bool, queenside: bool, own: &[Bitboard; 6], opp: &[Bitboard; 6],
+legal_moves:&mut Vec<Move>) {
+
+    // the back rank each side starts on
+    let home_rank: u8 = color.home_rank();
+    let on_home_rank = |file: u8, kind: PieceKind| own[kind.index()] & square_bit(Square::new(file, home_rank)) != 0;
+
+    // the king has to still be on its starting square
+    if !on_home_rank(4, PieceKind::King) {
+        return;
+    }
+
+    let occupied: Bitboard = own.iter().chain(opp.iter()).fold(0, |all, board| all | board);
+    let empty = |file: u8| occupied & square_bit(Square::new(file, home_rank)) == 0;
+    let safe = |file: u8| !square_attacked(Square::new(file, home_rank), color.opponent(), opp, occupied);
+
+    // can't castle out of check
+    if !safe(4) {
+        return;
+    }
+
+    let king_square = Square::new(4, home_rank);
+    if kingside && on_home_rank(7, PieceKind::Rook) && empty(5) && empty(6) && safe(5) && safe(6) {
+        legal_moves.push(Move::new(king_square, Square::new(6, home_rank)));
+    }
+
+    // on the queen's side the b file square must be empty, but it may be attacked
+    if queenside && on_home_rank(0, PieceKind::Rook) && empty(1) && empty(2) && empty(3) && safe(3) && safe(2) {
+        legal_moves.push(Move::new(king_square, Square::new(2, home_rank)));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(name: &str) -> Square {
+        return Square::from_name(name).unwrap();
+    }
+
+    /* Bitboard with each of the named squares set */
+    fn board(names: &[&str]) -> Bitboard {
+        return names.iter().fold(0, |board, name| board | square_bit(square(name)));
+    }
+
+    /* The moves as sorted UCI strings, so they can be compared against a list */
+    fn names(moves: &[Move]) -> Vec<String> {
+        let mut names: Vec<String> = moves.iter().map(|action| action.to_string()).collect();
+        names.sort();
+        return names;
+    }
+
+    #[test]
+    fn pawn_pushes_one_or_two_from_start() {
+        let mut moves = Vec::new();
+        pawn_legal_moves(Color::White, square("e2"), board(&["e2"]), 0, None, &mut moves);
+        assert_eq!(names(&moves), ["e2e3", "e2e4"]);
+
+        let mut moves = Vec::new();
+        pawn_legal_moves(Color::Black, square("d7"), board(&["d7"]), 0, None, &mut moves);
+        assert_eq!(names(&moves), ["d7d5", "d7d6"]);
+    }
+
+    #[test]
+    fn pawn_double_push_needs_both_squares_empty() {
+        let mut moves = Vec::new();
+        pawn_legal_moves(Color::White, square("e2"), board(&["e2"]), board(&["e3"]), None, &mut moves);
+        assert!(moves.is_empty());
+
+        let mut moves = Vec::new();
+        pawn_legal_moves(Color::White, square("e2"), board(&["e2"]), board(&["e4"]), None, &mut moves);
+        assert_eq!(names(&moves), ["e2e3"]);
+    }
+
+    #[test]
+    fn pawn_only_pushes_once_after_leaving_start() {
+        let mut moves = Vec::new();
+        pawn_legal_moves(Color::White, square("e3"), board(&["e3"]), 0, None, &mut moves);
+        assert_eq!(names(&moves), ["e3e4"]);
+    }
+
+    #[test]
+    fn pawn_captures_diagonally_but_not_own_pieces() {
+        let mut moves = Vec::new();
+        pawn_legal_moves(Color::White, square("e4"), board(&["e4", "f5"]), board(&["d5", "e5"]), None, &mut moves);
+        assert_eq!(names(&moves), ["e4d5"]);
+    }
+
+    #[test]
+    fn pawn_captures_en_passant() {
+        let mut moves = Vec::new();
+        pawn_legal_moves(Color::White, square("e5"), board(&["e5"]), board(&["d5", "d6"]), Some(square("f6")), &mut moves);
+        assert_eq!(names(&moves), ["e5d6", "e5e6", "e5f6"]);
+    }
+
+    #[test]
+    fn pawn_promotes_to_each_piece() {
+        let mut moves = Vec::new();
+        pawn_legal_moves(Color::Black, square("b2"), board(&["b2"]), board(&["a1", "b1"]), None, &mut moves);
+        assert_eq!(names(&moves), ["b2a1b", "b2a1n", "b2a1q", "b2a1r"]);
+    }
+
+    #[test]
+    fn knight_jumps_from_corner_and_centre() {
+        let mut moves = Vec::new();
+        knight_legal_moves(square("a1"), board(&["a1"]), &mut moves);
+        assert_eq!(names(&moves), ["a1b3", "a1c2"]);
+
+        let mut moves = Vec::new();
+        knight_legal_moves(square("d4"), board(&["d4", "e6"]), &mut moves);
+        assert_eq!(names(&moves), ["d4b3", "d4b5", "d4c2", "d4c6", "d4e2", "d4f3", "d4f5"]);
+    }
+
+    #[test]
+    fn king_steps_one_square_around_own_pieces() {
+        let mut moves = Vec::new();
+        king_legal_moves(square("h1"), board(&["h1", "g2"]), &mut moves);
+        assert_eq!(names(&moves), ["h1g1", "h1h2"]);
+    }
+
+    #[test]
+    fn rook_stops_before_own_piece_and_on_capture() {
+        let mut moves = Vec::new();
+        rook_legal_moves(square("a1"), board(&["a1", "a3"]), board(&["c1"]), &mut moves);
+        assert_eq!(names(&moves), ["a1a2", "a1b1", "a1c1"]);
+    }
+
+    #[test]
+    fn bishop_moves_along_diagonals() {
+        let mut moves = Vec::new();
+        bishop_legal_moves(square("c1"), board(&["c1", "b2"]), board(&["f4"]), &mut moves);
+        assert_eq!(names(&moves), ["c1d2", "c1e3", "c1f4"]);
+    }
+
+    #[test]
+    fn queen_moves_like_rook_and_bishop() {
+        let mut moves = Vec::new();
+        queen_legal_moves(square("d4"), board(&["d4"]), 0, &mut moves);
+        assert_eq!(moves.len(), 27);
+
+        let mut moves = Vec::new();
+        queen_legal_moves(square("a1"), board(&["a1", "a2", "b1"]), board(&["b2"]), &mut moves);
+        assert_eq!(names(&moves), ["a1b2"]);
+    }
+
+    #[test]
+    fn square_attacked_by_each_piece() {
+        let mut attacker = [0; 6];
+        attacker[PieceKind::Pawn.index()] = board(&["d5"]);
+        attacker[PieceKind::Knight.index()] = board(&["g8"]);
+        attacker[PieceKind::Rook.index()] = board(&["a6"]);
+        let occupied = board(&["d5", "g8", "a6", "c6"]);
+
+        // a black pawn on d5 attacks c4 and e4, not d4
+        assert!(square_attacked(square("e4"), Color::Black, &attacker, occupied));
+        assert!(!square_attacked(square("d4"), Color::Black, &attacker, occupied));
+        assert!(square_attacked(square("f6"), Color::Black, &attacker, occupied));
+        assert!(square_attacked(square("b6"), Color::Black, &attacker, occupied));
+        // the rook is blocked by the piece on c6
+        assert!(!square_attacked(square("d6"), Color::Black, &attacker, occupied));
+    }
+
+    #[test]
+    fn castling_both_ways_when_clear() {
+        let mut own = [0; 6];
+        own[PieceKind::King.index()] = board(&["e1"]);
+        own[PieceKind::Rook.index()] = board(&["a1", "h1"]);
+        let opp = [0; 6];
+
+        let mut moves = Vec::new();
+        castling_legal_moves(Color::White, true, true, &own, &opp, &mut moves);
+        assert_eq!(names(&moves), ["e1c1", "e1g1"]);
+
+        // without the rights there's nothing to do
+        let mut moves = Vec::new();
+        castling_legal_moves(Color::White, false, false, &own, &opp, &mut moves);
+        assert!(moves.is_empty());
+    }
+
+    #[test]
+    fn castling_not_through_check_or_pieces() {
+        let mut own = [0; 6];
+        own[PieceKind::King.index()] = board(&["e8"]);
+        own[PieceKind::Rook.index()] = board(&["a8", "h8"]);
+        own[PieceKind::Knight.index()] = board(&["b8"]);
+        let mut opp = [0; 6];
+        opp[PieceKind::Rook.index()] = board(&["f1"]);
+
+        // the rook on f1 covers f8, and the knight blocks the queen side
+        let mut moves = Vec::new();
+        castling_legal_moves(Color::Black, true, true, &own, &opp, &mut moves);
+        assert!(moves.is_empty());
+
+        // on the queen side only b8 may be attacked, it just has to be empty
+        own[PieceKind::Knight.index()] = 0;
+        opp[PieceKind::Rook.index()] = board(&["b1"]);
+        let mut moves = Vec::new();
+        castling_legal_moves(Color::Black, true, true, &own, &opp, &mut moves);
+        assert_eq!(names(&moves), ["e8c8", "e8g8"]);
+    }
+
+    #[test]
+    fn no_castling_out_of_check() {
+        let mut own = [0; 6];
+        own[PieceKind::King.index()] = board(&["e1"]);
+        own[PieceKind::Rook.index()] = board(&["a1", "h1"]);
+        let mut opp = [0; 6];
+        opp[PieceKind::Bishop.index()] = board(&["b4"]);
+
+        let mut moves = Vec::new();
+        castling_legal_moves(Color::White, true, true, &own, &opp, &mut moves);
+        assert!(moves.is_empty());
     }
 }
@@ -0,0 +1,18 @@
+#![allow(clippy::needless_return)]
+
+pub mod types;
+pub mod bitboard;
+pub mod magic;
+pub mod zobrist;
+pub mod state;
+pub mod attacks;
+pub mod game_status;
+pub mod fen;
+pub mod legal_moves;
+pub mod illegal_move;
+pub mod minimax;
+pub mod transposition;
+pub mod play_game;
+pub mod san;
+pub mod pgn;
+pub mod perft;
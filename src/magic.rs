@@ -0,0 +1,173 @@
+use std::sync::OnceLock;
+use crate::bitboard::{self, Bitboard};
+use crate::types::Square;
+
+// The a and h files, and the 1st and 8th ranks. A slider always reaches an edge square if nothing is in the
+// way, so pieces there never change the attacks and are left out of the occupancy masks
+const FILE_EDGES: Bitboard = 0x8181_8181_8181_8181;
+const RANK_EDGES: Bitboard = 0xFF00_0000_0000_00FF;
+
+// Seed for the random number generator used to search for magic numbers. Fixed so startup always
+// builds the same tables
+const SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+// Number of magic numbers to try for a square before giving it a bigger table section
+const MAX_ATTEMPTS: u32 = 1_000;
+
+/*
+Lookup details for one slider on one square. The occupied squares that can block the slider are
+multiplied by magic, and the top bits of the result index that square's section of the attack table
+*/
+#[derive(Clone, Copy, Default)]
+struct Magic {
+    // Squares whose occupancy changes the slider's attacks
+    mask: Bitboard,
+    magic: u64,
+    // 64 minus the number of bits in the index
+    shift: u32,
+    // Where this square's attacks start in the shared attack table
+    offset: usize,
+}
+
+impl Magic {
+    /* Position in the attack table of the attacks for this occupancy */
+    fn index(&self, occupied: Bitboard) -> usize {
+        return self.offset + ((occupied & self.mask).wrapping_mul(self.magic) >> self.shift) as usize;
+    }
+}
+
+/* Magic numbers for rooks and bishops on every square, and the attack table they index into */
+struct MagicTables {
+    rook: [Magic; 64],
+    bishop: [Magic; 64],
+    attacks: Vec<Bitboard>,
+}
+
+static TABLES: OnceLock<MagicTables> = OnceLock::new();
+
+/* The tables are built the first time a slider's attacks are looked up */
+fn tables() -> &'static MagicTables {
+    return TABLES.get_or_init(MagicTables::generate);
+}
+
+/* The squares a rook on square attacks, given every occupied square on the board */
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    return tables.attacks[tables.rook[square.index() as usize].index(occupied)];
+}
+
+/* The squares a bishop on square attacks, given every occupied square on the board */
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    let tables = tables();
+    return tables.attacks[tables.bishop[square.index() as usize].index(occupied)];
+}
+
+impl MagicTables {
+    /* Search for a working magic number for every square, filling in the attack table as it goes */
+    fn generate() -> Self {
+        let mut tables = MagicTables {
+            rook: [Magic::default(); 64],
+            bishop: [Magic::default(); 64],
+            attacks: Vec::new(),
+        };
+        let mut rng = Rng(SEED);
+
+        for index in 0..64 {
+            let square = Square::from_index(index);
+            tables.rook[index as usize] = tables.find_magic(square, &bitboard::STRAIGHT_DIRECTIONS, &mut rng);
+            tables.bishop[index as usize] = tables.find_magic(square, &bitboard::DIAGONAL_DIRECTIONS, &mut rng);
+        }
+        return tables;
+    }
+
+    /*
+    Find a magic number for a slider moving in directions from square. Random sparse numbers are tried
+    until one sends every occupancy to a table entry that holds the right attacks. Different occupancies
+    can share an entry as long as they give the same attacks
+    */
+    fn find_magic(&mut self, square: Square, directions: &[(i8, i8)], rng: &mut Rng) -> Magic {
+        // edges only count when the slider is on them, so a rook on the a file can still be blocked along it
+        let edges = (RANK_EDGES & !rank_squares(square)) | (FILE_EDGES & !file_squares(square));
+        let mask = bitboard::sliding_attacks(square, directions, 0) & !edges;
+        let bits = mask.count_ones();
+
+        // every subset of the mask, with the attacks the slider has for it
+        let mut occupancies: Vec<Bitboard> = Vec::with_capacity(1 << bits);
+        let mut answers: Vec<Bitboard> = Vec::with_capacity(1 << bits);
+        let mut subset: Bitboard = 0;
+        loop {
+            occupancies.push(subset);
+            answers.push(bitboard::sliding_attacks(square, directions, subset));
+            subset = subset.wrapping_sub(mask) & mask;
+            if subset == 0 {
+                break;
+            }
+        }
+
+        // a few squares are slow to find a magic for. If none turns up after a while, a bigger section of the
+        // table is used, which makes a working magic much easier to find
+        let offset = self.attacks.len();
+        let mut index_bits = bits;
+        loop {
+            let size = 1 << index_bits;
+            self.attacks.resize(offset + size, 0);
+
+            // which attempt last wrote each entry, so the section doesn't need clearing between attempts
+            let mut written: Vec<u32> = vec![0; size];
+            let mut attempt: u32 = 0;
+            while attempt < MAX_ATTEMPTS {
+                let magic = rng.sparse();
+                // magics that spread the mask's bits over too few of the top bits rarely work, skip them early
+                if (mask.wrapping_mul(magic) >> 56).count_ones() < 6 {
+                    continue;
+                }
+                attempt += 1;
+
+                let candidate = Magic { mask, magic, shift: 64 - index_bits, offset };
+                let mut works = true;
+                for (occupied, attacks) in occupancies.iter().zip(answers.iter()) {
+                    let index = candidate.index(*occupied);
+                    if written[index - offset] != attempt {
+                        written[index - offset] = attempt;
+                        self.attacks[index] = *attacks;
+                    }
+                    else if self.attacks[index] != *attacks {
+                        works = false;
+                        break;
+                    }
+                }
+                if works {
+                    return candidate;
+                }
+            }
+            index_bits += 1;
+        }
+    }
+}
+
+/* Every square on the same rank as square */
+fn rank_squares(square: Square) -> Bitboard {
+    return 0xFF << (square.rank() * 8);
+}
+
+/* Every square on the same file as square */
+fn file_squares(square: Square) -> Bitboard {
+    return bitboard::FILE_A << square.file();
+}
+
+/* Small xorshift random number generator, only used to search for magic numbers */
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        return self.0.wrapping_mul(0x2545_F491_4F6C_DD1D);
+    }
+
+    /* A random number with only a few bits set, which make better magic candidates */
+    fn sparse(&mut self) -> u64 {
+        return self.next() & self.next() & self.next();
+    }
+}
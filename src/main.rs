@@ -1,8 +1,76 @@
-pub mod state;
-pub mod legal_moves;
-pub mod minimax;
-pub mod play_game;
+#![allow(clippy::needless_return)]
+
+use std::env;
+use std::time::Duration;
+use chess_bot::{perft, play_game};
+use chess_bot::minimax::TimeControl;
 
 fn main() {
-    play_game::play_game();
-}
\ No newline at end of file
+    let args: Vec<String> = env::args().collect();
+
+    // perft <depth> [fen] counts the positions reached from fen instead of playing a game
+    if args.len() > 1 && args[1] == "perft" {
+        let depth: Option<u32> = args.get(2).and_then(|depth| depth.parse().ok());
+        let Some(depth) = depth else {
+            eprintln!("Usage: chess_bot perft <depth> [fen]");
+            return;
+        };
+        // the FEN may or may not have been quoted, so join whatever is left back together
+        let fen: String = args[3..].join(" ");
+        let fen: Option<&str> = if fen.is_empty() { None } else { Some(&fen) };
+        if let Err(err) = perft::run_perft(depth, fen) {
+            eprintln!("Invalid FEN: {}", err);
+        }
+        return;
+    }
+
+    // --pgn <file> saves the game to file once it's over. --movetime <seconds> gives the computer a fixed
+    // time per move, --clock <minutes>+<seconds> plays on a clock with an increment
+    let mut pgn_path: Option<String> = None;
+    let mut time_control = TimeControl::FixedTime(Duration::from_secs(3));
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--pgn" if i + 1 < args.len() => {
+                pgn_path = Some(args[i + 1].clone());
+                i += 1;
+            },
+            "--movetime" if i + 1 < args.len() => {
+                let Some(seconds) = args[i + 1].parse::<f64>().ok().filter(|seconds: &f64| seconds.is_finite() && *seconds > 0.0) else {
+                    eprintln!("Invalid move time '{}', expected a number of seconds", args[i + 1]);
+                    return;
+                };
+                time_control = TimeControl::FixedTime(Duration::from_secs_f64(seconds));
+                i += 1;
+            },
+            "--clock" if i + 1 < args.len() => {
+                let Some(clock) = parse_clock(&args[i + 1]) else {
+                    eprintln!("Invalid clock '{}', expected <minutes>+<seconds> like 5+3", args[i + 1]);
+                    return;
+                };
+                time_control = clock;
+                i += 1;
+            },
+            arg => {
+                eprintln!("Unknown argument '{}'. {}", arg, USAGE);
+                return;
+            }
+        }
+        i += 1;
+    }
+
+    play_game::play_game(pgn_path, time_control);
+}
+
+const USAGE: &str = "Usage: chess_bot [--pgn <file>] [--movetime <seconds> | --clock <minutes>+<seconds>] | perft <depth> [fen]";
+
+/* Parse a clock like 5+3, five minutes each with three seconds added after every move */
+fn parse_clock(clock: &str) -> Option<TimeControl> {
+    let (minutes, increment) = clock.split_once('+').unwrap_or((clock, "0"));
+    let minutes: f64 = minutes.parse().ok().filter(|minutes: &f64| minutes.is_finite() && *minutes > 0.0)?;
+    let increment: f64 = increment.parse().ok().filter(|increment: &f64| increment.is_finite() && *increment >= 0.0)?;
+    return Some(TimeControl::Clock {
+        remaining: Duration::from_secs_f64(minutes * 60.0),
+        increment: Duration::from_secs_f64(increment),
+    });
+}
@@ -1,150 +1,538 @@
-use std::collections::HashSet;
-use std::collections::HashMap;
-use crate::state::{State};
+use std::time::{Duration, Instant};
+use crate::state::State;
+use crate::transposition::{Bound, TranspositionTable};
+use crate::types::{Move, PieceKind};
+use crate::game_status::GameStatus;
+
+// Larger than any score the search can return, used as the starting alpha-beta window. Kept well inside
+// i16 so it can be negated safely
+const INFINITY: i16 = 30000;
+
+// Score for checkmating on the spot. A mate n moves from the root scores MATE - n, so nearer mates score higher
+const MATE: i16 = 10000;
+
+// Deepest iteration iterative deepening will start
+const MAX_DEPTH: u16 = 64;
+
+// Any score further from 0 than this is a forced mate
+const MATE_THRESHOLD: i16 = MATE - MAX_DEPTH as i16;
+
+// How many nodes are searched between checks of the clock
+const NODES_PER_TIME_CHECK: u64 = 2048;
+
+// Move ordering scores for each kind of move. Every capture sorts ahead of every killer, and every killer ahead
+// of every other quiet move, whatever its history
+const HASH_MOVE_SCORE: i32 = 3_000_000;
+const CAPTURE_SCORE: i32 = 2_000_000;
+const KILLER_SCORE: i32 = 1_000_000;
+const HISTORY_MAX: i32 = KILLER_SCORE - 1;
+
+// How much a capture may gain on top of the piece it takes, for delta pruning. Covers the evaluation changing
+// for other reasons along the way
+const DELTA_MARGIN: i16 = 2;
 
-#[derive(Default)]
 pub struct MinimaxResult {
+    // Score of the best move, from the point of view of the side to move
     pub minimax_val: i16,
-    pub action: [i8; 4],
+    pub action: Move,
+    // How many moves ahead the search that found the move looked
+    pub depth: u16,
 }
 
+/* How long the engine may think about a move */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimeControl {
+    // The same amount of time for every move
+    FixedTime(Duration),
+    // What's left on the engine's clock, and the time added to it after each move
+    Clock { remaining: Duration, increment: Duration },
+}
+
+impl TimeControl {
+    /*
+    How long to spend on the next move. On a clock, a small share of the remaining time is used, so there's
+    always some left for later moves, plus most of the increment since that comes back after the move
+    */
+    pub fn budget(&self) -> Duration {
+        return match self {
+            TimeControl::FixedTime(time) => *time,
+            TimeControl::Clock { remaining, increment } => {
+                let budget = *remaining / 30 + *increment * 3 / 4;
+                budget.min(*remaining / 2)
+            }
+        };
+    }
+}
 
-/* Returns the best move that can be taken given a state and depth-limit */
-pub fn search_max(cur_state: &State, depth_limit: u16) -> MinimaxResult {
-    let mut best_action: [i8; 4] = [0; 4];
-    let alpha: i16 = i16::MIN;
-    let beta:i16 = i16::MAX;
-    let mut best: i16 = i16::MIN;
 
+/*
+Returns the best move for the side to move, searching depth_limit moves ahead. Uses negamax: each side
+picks the move that's worst for the other, so one search works for both colours. table keeps what was
+found, and can be passed to the search for the next move. A depth of 0 is treated as 1, since a move has to
+be tried to be picked. Returns None if the side to move has no legal moves, because the game is over
+*/
+pub fn search(cur_state: &State, depth_limit: u16, table: &mut TranspositionTable) -> Option<MinimaxResult> {
+    if cur_state.generate_legal_moves().is_empty() {
+        return None;
+    }
+    let depth_limit = depth_limit.max(1);
+    table.new_search();
+    // the search makes and takes back moves on its own copy of the state
+    let mut state = cur_state.clone();
+    let mut search = Search::new(None, table);
+    return search.root(&mut state, depth_limit);
+}
 
-    let mut transposition_table: HashMap<State, i16> = Default::default();
 
+/*
+Returns the best move for the side to move found within the time control's budget. Searches one move
+ahead, then two, and so on until time runs out. An iteration that's cut short is thrown away, so the move
+comes from the deepest search that finished. Returns None if the side to move has no legal moves
+*/
+pub fn search_timed(cur_state: &State, time: TimeControl, table: &mut TranspositionTable) -> Option<MinimaxResult> {
+    if cur_state.generate_legal_moves().is_empty() {
+        return None;
+    }
+    let start = Instant::now();
+    let budget = time.budget();
+    let mut state = cur_state.clone();
+    table.new_search();
 
-    let mut cur_val: i16;
+    // the first iteration always runs to the end, so there's a move to play however short the budget
+    let mut search = Search::new(None, table);
+    let mut best: MinimaxResult = search.root(&mut state, 1).expect("search without a deadline always finishes");
+    search.deadline = Some(start + budget);
 
-    let legal_moves: HashSet<[i8; 4]> = cur_state.generate_legal_moves();
-    for action in &legal_moves {
-        let mut new_state = cur_state.clone();
-        new_state.action_to_state(&action);
-        cur_val = min_recurse(&new_state, alpha, beta, depth_limit-1, &mut transposition_table);
+    for depth in 2..=MAX_DEPTH {
+        // an iteration usually takes several times longer than the last one, so don't start one that
+        // probably won't finish
+        if start.elapsed() >= budget / 2 {
+            break;
+        }
+        // no need to look further once a forced mate has been found
+        if best.minimax_val.abs() > MATE_THRESHOLD {
+            break;
+        }
 
-        if cur_val > best {
-            best = cur_val;
-            best_action = action.clone();
-        } 
+        // each iteration starts with the best move from the last, since it's stored at the root. The killers
+        // and history carry over too
+        match search.root(&mut state, depth) {
+            Some(result) => { best = result; },
+            None => { break; }
+        }
     }
-    let res = MinimaxResult{minimax_val: best, action: best_action};
-    return res;
+    return Some(best);
 }
 
 
-/* Returns the best move that can be taken given a state and depth-limit */
-pub fn search_min(cur_state: &State, depth_limit: u16) -> MinimaxResult {
-    let mut best_action: [i8; 4] = [0; 4];
-    let alpha: i16 = i16::MIN;
-    let beta:i16 = i16::MAX;
-    let mut best: i16 = i16::MAX;
+/*
+Mate scores count moves from the root, but a stored position can be reached at a different distance from it.
+Stored mate scores count from the position itself instead, and are converted back when they're read
+*/
+fn score_to_table(score: i16, ply: u16) -> i16 {
+    if score > MATE_THRESHOLD {
+        return score + ply as i16;
+    }
+    if score < -MATE_THRESHOLD {
+        return score - ply as i16;
+    }
+    return score;
+}
+
+fn score_from_table(score: i16, ply: u16) -> i16 {
+    if score > MATE_THRESHOLD {
+        return score - ply as i16;
+    }
+    if score < -MATE_THRESHOLD {
+        return score + ply as i16;
+    }
+    return score;
+}
 
-    let mut transposition_table: HashMap<State, i16> = Default::default();
-    
-    let mut cur_val: i16;
 
-    let legal_moves: HashSet<[i8; 4]> = cur_state.generate_legal_moves();
-    for action in &legal_moves {
-        let mut new_state = cur_state.clone();
-        new_state.action_to_state(&action);
-        cur_val = max_recurse(&new_state, alpha, beta, depth_limit-1, &mut transposition_table);
+/* Everything one depth-limited search needs to keep track of */
+struct Search<'a> {
+    // When to give up, if the search is on the clock
+    deadline: Option<Instant>,
+    nodes: u64,
+    // Set once the deadline has passed. Every node returns straight away after this
+    stopped: bool,
+    table: &'a mut TranspositionTable,
+    // Two quiet moves per ply that caused a cutoff there. Positions at the same ply are often similar, so they
+    // tend to work again
+    killers: [[Option<Move>; 2]; MAX_DEPTH as usize],
+    // How much each quiet move has caused cutoffs, indexed by Color::index, then from and to square
+    history: [[[i32; 64]; 64]; 2],
+}
 
-        if cur_val < best {
-            best = cur_val;
-            best_action = action.clone();
-        } 
+impl<'a> Search<'a> {
+    fn new(deadline: Option<Instant>, table: &'a mut TranspositionTable) -> Self {
+        return Search {
+            deadline,
+            nodes: 0,
+            stopped: false,
+            table,
+            killers: [[None; 2]; MAX_DEPTH as usize],
+            history: [[[0; 64]; 64]; 2],
+        };
     }
-    let res = MinimaxResult{minimax_val: best, action: best_action};
-    return res;
-}
 
+    /*
+    Sort moves so the ones most likely to be best are searched first, since a good move found early lets
+    alpha-beta skip more of the rest. The stored best move goes first, then captures with the most valuable
+    victim taken by the least valuable attacker, then the killers for this ply, then other quiet moves by
+    history
+    */
+    fn order_moves(&self, state: &State, moves: &mut [Move], hash_move: Option<Move>, ply: u16) {
+        let color = state.side_to_move().index();
+        // quiescence can go deeper than the killers are kept for
+        let killers = self.killers.get(ply as usize).copied().unwrap_or_default();
+        moves.sort_by_cached_key(|action| {
+            if Some(*action) == hash_move {
+                return -HASH_MOVE_SCORE;
+            }
+            // promotions sort with the captures, as if capturing the piece the pawn becomes
+            if Search::is_tactical(state, action) {
+                let attacker = state.piece_at(action.from).expect("moving piece is on the board").kind;
+                let gain = Search::captured(state, action).map_or(0, State::piece_value) +
+                    action.promotion.map_or(0, State::piece_value);
+                return -(CAPTURE_SCORE + gain as i32 * 1000 - State::piece_value(attacker) as i32);
+            }
+            if let Some(index) = killers.iter().position(|killer| *killer == Some(*action)) {
+                return -(KILLER_SCORE - index as i32);
+            }
+            return -self.history[color][action.from.index() as usize][action.to.index() as usize];
+        });
+    }
 
-fn max_recurse(cur_state: &State, mut alpha: i16, beta: i16, depth_limit: u16, transposition_table: &mut HashMap<State, i16>) -> i16 {
-    if cur_state.victory_check() == -1 {
-        return -1000;
+    /* Remember a quiet move that caused a cutoff, so it's tried earlier next time */
+    fn record_cutoff(&mut self, state: &State, action: Move, depth_limit: u16, ply: u16) {
+        // searches deeper than MAX_DEPTH don't keep killers past it
+        if let Some(killers) = self.killers.get_mut(ply as usize) && killers[0] != Some(action) {
+            killers[1] = killers[0];
+            killers[0] = Some(action);
+        }
+        // deeper searches are worth more, since they cut off bigger subtrees
+        let color = state.side_to_move().index();
+        let entry = &mut self.history[color][action.from.index() as usize][action.to.index() as usize];
+        *entry = (*entry + depth_limit as i32 * depth_limit as i32).min(HISTORY_MAX);
     }
-    if cur_state.victory_check() == 1 {
-        return 1000;
+
+    /* The kind of piece action takes, if any. A pawn moving onto the en passant square takes a pawn */
+    fn captured(state: &State, action: &Move) -> Option<PieceKind> {
+        if let Some(piece) = state.piece_at(action.to) {
+            return Some(piece.kind);
+        }
+        let pawn_moves = state.piece_at(action.from).is_some_and(|piece| piece.kind == PieceKind::Pawn);
+        if pawn_moves && state.en_passant_square() == Some(action.to) {
+            return Some(PieceKind::Pawn);
+        }
+        return None;
     }
 
-    if depth_limit == 0 {
-        return cur_state.estimate_minimax();
+    /* Whether action takes a piece or promotes, as opposed to a quiet move */
+    fn is_tactical(state: &State, action: &Move) -> bool {
+        return Search::captured(state, action).is_some() || action.promotion.is_some();
     }
 
-    // this state has already been reached, can just return previously computed
-    // minimax value
-    if transposition_table.contains_key(cur_state) {
-        return *transposition_table.get(cur_state).unwrap();
+    /* Counts a node, and checks every so often whether the deadline has passed */
+    fn out_of_time(&mut self) -> bool {
+        self.nodes += 1;
+        if !self.stopped && self.nodes.is_multiple_of(NODES_PER_TIME_CHECK) && let Some(deadline) = self.deadline {
+            self.stopped = Instant::now() >= deadline;
+        }
+        return self.stopped;
     }
 
-    let mut cur_val: i16;
-    let mut best: i16 = i16::MIN;
+    /*
+    Search every move from the root, trying the stored best move first. The state must have legal moves.
+    Returns None if the deadline passed before the search finished, since the result can't be trusted
+    */
+    fn root(&mut self, state: &mut State, depth_limit: u16) -> Option<MinimaxResult> {
+        let mut best_action: Option<Move> = None;
+        let mut alpha: i16 = -INFINITY;
+        let beta: i16 = INFINITY;
+        let mut best: i16 = -INFINITY;
+
+        let mut legal_moves: Vec<Move> = state.generate_legal_moves();
+        // the best move from the last iteration is likely still best, and finding a good move early prunes more
+        let hash_move = self.table.probe(state.zobrist_key()).and_then(|entry| entry.best_move);
+        self.order_moves(state, &mut legal_moves, hash_move, 0);
+
+        for action in &legal_moves {
+            let undo = state.make_move(action);
+            let cur_val = -self.negamax(state, -beta, -alpha, depth_limit - 1, 1);
+            state.unmake_move(action, undo);
+
+            if self.stopped {
+                return None;
+            }
+            if cur_val > best || best_action.is_none() {
+                best = cur_val;
+                best_action = Some(*action);
+            }
+            alpha = std::cmp::max(alpha, best);
+        }
+        let action = best_action.expect("callers check there are legal moves");
+        self.table.store(state.zobrist_key(), depth_limit, Bound::Exact, best, Some(action));
+        let res = MinimaxResult{minimax_val: best, action, depth: depth_limit};
+        return Some(res);
+    }
 
-    let legal_moves: HashSet<[i8; 4]> = cur_state.generate_legal_moves();
-    for action in &legal_moves {
-        let mut new_state = cur_state.clone();
-        new_state.action_to_state(&action);
-        cur_val = min_recurse(&new_state, alpha, beta, depth_limit-1, transposition_table);
+    /*
+    Score cur_state for the side to move, looking depth_limit moves ahead. alpha is the score the side to
+    move is already guaranteed elsewhere, beta the most the opponent will allow. Once a move reaches beta the
+    opponent won't let this position happen, so the rest of the moves can be skipped. ply is how many moves
+    cur_state is from the root
+    */
+    fn negamax(&mut self, cur_state: &mut State, mut alpha: i16, beta: i16, depth_limit: u16, ply: u16) -> i16 {
+        if self.out_of_time() {
+            return 0;
+        }
 
-        if cur_val > best {
-            best = cur_val;
+        if cur_state.draw_reason().is_some() {
+            return 0;
         }
-        /* If min finds a lower value than the current best, we can continue */
-        if best >= beta {
-            return best;
+
+        if depth_limit == 0 {
+            return self.quiescence(cur_state, alpha, beta, ply);
         }
-        alpha = std::cmp::max(alpha, best);
+
+        // this state has already been searched at least as deep. The stored score can be used if it's exact,
+        // or if its bound alone is enough to settle the position against this window
+        let key = cur_state.zobrist_key();
+        let stored = self.table.probe(key);
+        if let Some(entry) = stored && entry.depth >= depth_limit {
+            let score = score_from_table(entry.score, ply);
+            match entry.bound {
+                Bound::Exact => { return score; },
+                Bound::Lower if score >= beta => { return score; },
+                Bound::Upper if score <= alpha => { return score; },
+                _ => {}
+            }
+        }
+
+        let mut legal_moves: Vec<Move> = cur_state.generate_legal_moves();
+
+        // the side to move has been mated
+        match cur_state.status_from_moves(&legal_moves) {
+            GameStatus::Ongoing => {},
+            GameStatus::Checkmate { .. } => { return -MATE + ply as i16; },
+            _ => { return 0; }
+        }
+
+        self.order_moves(cur_state, &mut legal_moves, stored.and_then(|entry| entry.best_move), ply);
+
+        let original_alpha = alpha;
+        let mut best: i16 = -INFINITY;
+        let mut best_action: Option<Move> = None;
+        for action in &legal_moves {
+            let undo = cur_state.make_move(action);
+            let cur_val = -self.negamax(cur_state, -beta, -alpha, depth_limit - 1, ply + 1);
+            cur_state.unmake_move(action, undo);
+
+            if cur_val > best {
+                best = cur_val;
+                best_action = Some(*action);
+            }
+            /* The opponent already has a better option than letting this position happen */
+            if best >= beta {
+                if !Search::is_tactical(cur_state, action) {
+                    self.record_cutoff(cur_state, *action, depth_limit, ply);
+                }
+                break;
+            }
+            alpha = std::cmp::max(alpha, best);
+        }
+
+        // a search cut short by the clock hasn't really scored this position
+        if !self.stopped {
+            let bound = if best >= beta {
+                Bound::Lower
+            }
+            else if best <= original_alpha {
+                // no move reached alpha, so none of them is known to be best
+                best_action = None;
+                Bound::Upper
+            }
+            else {
+                Bound::Exact
+            };
+            self.table.store(key, depth_limit, bound, score_to_table(best, ply), best_action);
+        }
+        return best;
+    }
+
+    /*
+    Keep searching captures and promotions past the depth limit until the position is quiet, so a piece left
+    hanging just beyond the horizon isn't scored as safe. The side to move can usually do at least as well as
+    the current evaluation by making a quiet move, so that's taken as a floor ("standing pat"), except in check
+    where every way out has to be searched
+    */
+    fn quiescence(&mut self, cur_state: &mut State, mut alpha: i16, beta: i16, ply: u16) -> i16 {
+        if self.out_of_time() {
+            return 0;
+        }
+
+        let in_check = cur_state.in_check();
+        let stand_pat = cur_state.evaluate();
+        if !in_check {
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = std::cmp::max(alpha, stand_pat);
+        }
+
+        let mut moves: Vec<Move> = cur_state.generate_legal_moves();
+        if in_check && moves.is_empty() {
+            return -MATE + ply as i16;
+        }
+        if !in_check {
+            moves.retain(|action| Search::is_tactical(cur_state, action));
+        }
+        self.order_moves(cur_state, &mut moves, None, ply);
+
+        let mut best: i16 = if in_check { -INFINITY } else { stand_pat };
+        for action in &moves {
+            // delta pruning: skip captures that can't bring the score back up to alpha even taking the piece
+            // for free
+            let gain = Search::captured(cur_state, action).map_or(0, State::piece_value) +
+                action.promotion.map_or(0, State::piece_value);
+            if !in_check && stand_pat + gain + DELTA_MARGIN < alpha {
+                continue;
+            }
+
+            let undo = cur_state.make_move(action);
+            let cur_val = -self.quiescence(cur_state, -beta, -alpha, ply + 1);
+            cur_state.unmake_move(action, undo);
+
+            if cur_val > best {
+                best = cur_val;
+            }
+            if best >= beta {
+                break;
+            }
+            alpha = std::cmp::max(alpha, best);
+        }
+        return best;
     }
-    transposition_table.insert(cur_state.clone(), best);
-    return best;
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Color;
 
-fn min_recurse(cur_state: &State, alpha: i16, mut beta: i16, depth_limit: u16, transposition_table: &mut HashMap<State, i16>) -> i16 {
-    if cur_state.victory_check() == -1 {
-        return -1000;
+    fn table() -> TranspositionTable {
+        return TranspositionTable::new(1);
     }
-    if cur_state.victory_check() == 1 {
-        return 1000;
-    } 
 
-    if depth_limit == 0 {
-        return cur_state.estimate_minimax();
+    #[test]
+    fn finds_mate_in_one() {
+        let state = State::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let result = search(&state, 3, &mut table()).unwrap();
+        assert_eq!(result.action.to_string(), "a1a8");
+        assert_eq!(result.minimax_val, MATE - 1);
+    }
+
+    #[test]
+    fn same_score_for_either_colour() {
+        // negamax scores from the side to move, so a position and its colour-flipped mirror score the same
+        let white = State::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let black = State::from_fen("r3k2r/pppbbppp/2n2q1P/1P2p3/3pn3/BN2PNP1/P1PPQPB1/R3K2R b KQkq - 0 1").unwrap();
+        for depth in 1..=3 {
+            let white_result = search(&white, depth, &mut table()).unwrap();
+            let black_result = search(&black, depth, &mut table()).unwrap();
+            assert_eq!(white_result.minimax_val, black_result.minimax_val);
+        }
     }
 
-    // this state has already been reached, can just return previously computed
-    // minimax value
-    if transposition_table.contains_key(&cur_state) {
-        return *transposition_table.get(&cur_state).unwrap();
+    #[test]
+    fn nothing_to_search_once_the_game_is_over() {
+        // checkmate, then stalemate
+        for fen in ["R5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 1 1", "7k/5Q2/8/8/8/8/8/6K1 b - - 0 1"] {
+            let state = State::from_fen(fen).unwrap();
+            assert!(search(&state, 3, &mut table()).is_none());
+            assert!(search_timed(&state, TimeControl::FixedTime(Duration::from_millis(10)), &mut table()).is_none());
+        }
     }
 
-    let mut cur_val: i16;
-    let mut best: i16 = i16::MAX;
+    #[test]
+    fn quiescence_sees_the_recapture() {
+        // the pawn on d5 is defended, so taking it loses the queen one move past the horizon
+        let state = State::from_fen("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let result = search(&state, 1, &mut table()).unwrap();
+        assert_ne!(result.action.to_string(), "d1d5");
+        assert_eq!(result.minimax_val, state.evaluate());
+    }
 
-    let legal_moves: HashSet<[i8; 4]> = cur_state.generate_legal_moves();
+    #[test]
+    fn clock_budget_keeps_time_in_reserve() {
+        assert_eq!(TimeControl::FixedTime(Duration::from_secs(3)).budget(), Duration::from_secs(3));
 
-    for action in &legal_moves {
-        let mut new_state = cur_state.clone();
-        new_state.action_to_state(&action);
-        cur_val = max_recurse(&new_state, alpha, beta, depth_limit-1, transposition_table);
+        let clock = TimeControl::Clock { remaining: Duration::from_secs(300), increment: Duration::from_secs(4) };
+        assert_eq!(clock.budget(), Duration::from_secs(13));
 
-        if cur_val < best {
-            best = cur_val
-        }
+        // never more than half of what's left, however big the increment
+        let short = TimeControl::Clock { remaining: Duration::from_secs(2), increment: Duration::from_secs(10) };
+        assert_eq!(short.budget(), Duration::from_secs(1));
+    }
 
-        /* If max finds a better value than the current best, we can continue */
-        if best <= alpha {
-            return best;
+    #[test]
+    fn timed_search_returns_a_legal_move_in_time() {
+        let state = State::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let start = Instant::now();
+        let result = search_timed(&state, TimeControl::FixedTime(Duration::from_millis(50)), &mut table()).unwrap();
+        assert!(state.generate_legal_moves().contains(&result.action));
+        assert!(result.depth >= 1);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn timed_search_stops_once_mate_is_found() {
+        let state = State::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let start = Instant::now();
+        let result = search_timed(&state, TimeControl::FixedTime(Duration::from_secs(30)), &mut table()).unwrap();
+        assert_eq!(result.action.to_string(), "a1a8");
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn stored_scores_match_a_fresh_search() {
+        // searching again with a table full of shallower results shouldn't change the score
+        let state = State::from_fen("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8").unwrap();
+        let mut warm = table();
+        for depth in 1..=3 {
+            let fresh = search(&state, depth, &mut table()).unwrap().minimax_val;
+            assert_eq!(search(&state, depth, &mut warm).unwrap().minimax_val, fresh);
         }
-        beta = std::cmp::min(best, beta);
     }
-    transposition_table.insert(cur_state.clone(), best);
-    return best;
-}
 
+    #[test]
+    fn stored_mate_scores_count_from_the_position() {
+        // mate 5 moves from the root, found at ply 2, is mate in 3 from the stored position
+        let stored = score_to_table(MATE - 5, 2);
+        assert_eq!(stored, MATE - 3);
+        // read back 4 moves from the root it's mate in 7 from there
+        assert_eq!(score_from_table(stored, 4), MATE - 7);
+        assert_eq!(score_from_table(score_to_table(-MATE + 6, 3), 3), -MATE + 6);
+        // ordinary scores are stored as they are
+        assert_eq!(score_to_table(15, 9), 15);
+    }
+
+    #[test]
+    fn moves_ordered_hash_captures_killers_history() {
+        let state = State::from_fen("4k3/8/8/3q4/4P3/8/p7/Q3K3 w - - 0 1").unwrap();
+        let mut table = table();
+        let mut search = Search::new(None, &mut table);
+        let named = |name: &str| state.generate_legal_moves().into_iter().find(|action| action.to_string() == name).unwrap();
+        search.record_cutoff(&state, named("e1f2"), 1, 0);
+        search.history[Color::White.index()][named("e1e2").from.index() as usize][named("e1e2").to.index() as usize] = 100;
+
+        let mut moves = state.generate_legal_moves();
+        search.order_moves(&state, &mut moves, Some(named("a1b1")), 0);
+        let order: Vec<String> = moves.iter().take(5).map(|action| action.to_string()).collect();
+        // pawn takes queen before queen takes pawn
+        assert_eq!(order, ["a1b1", "e4d5", "a1a2", "e1f2", "e1e2"]);
+    }
+}
@@ -0,0 +1,81 @@
+use std::time::Instant;
+use crate::fen::{FenError, START_FEN};
+use crate::state::State;
+use crate::types::Move;
+
+impl State {
+    /*
+    Count the positions reached by playing every sequence of depth legal moves from this state. Checked
+    against published counts, this finds bugs in move generation
+    */
+    pub fn perft(&self, depth: u32) -> u64 {
+        let mut state = self.clone();
+        return perft_recurse(&mut state, depth);
+    }
+
+    /*
+    Perft split up by the first move. Each legal move is returned with the number of positions found
+    below it, sorted by move. Comparing this against another engine narrows a wrong count down to a move
+    */
+    pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        let mut state = self.clone();
+        let mut counts: Vec<(Move, u64)> = Vec::new();
+        if depth == 0 {
+            return counts;
+        }
+
+        for action in state.generate_legal_moves() {
+            let undo = state.make_move(&action);
+            counts.push((action, perft_recurse(&mut state, depth - 1)));
+            state.unmake_move(&action, undo);
+        }
+        counts.sort_by_key(|(action, _)| action.to_string());
+        return counts;
+    }
+}
+
+fn perft_recurse(state: &mut State, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let legal_moves: Vec<Move> = state.generate_legal_moves();
+
+    // the last level only needs counting, there's no need to play the moves
+    if depth == 1 {
+        return legal_moves.len() as u64;
+    }
+
+    let mut nodes: u64 = 0;
+    for action in &legal_moves {
+        let undo = state.make_move(action);
+        nodes += perft_recurse(state, depth - 1);
+        state.unmake_move(action, undo);
+    }
+    return nodes;
+}
+
+/*
+Run perft from the command line. Prints the divide counts for each move, then the total and how long it
+took. fen defaults to the starting position
+*/
+pub fn run_perft(depth: u32, fen: Option<&str>) -> Result<(), FenError> {
+    let state = State::from_fen(fen.unwrap_or(START_FEN))?;
+
+    let start = Instant::now();
+    let counts = state.divide(depth);
+    let elapsed = start.elapsed();
+
+    let mut total: u64 = counts.iter().map(|(_, nodes)| nodes).sum();
+    for (action, nodes) in &counts {
+        println!("{}: {}", action, nodes);
+    }
+    // divide has no moves to split by at depth 0, the only position is the current one
+    if depth == 0 {
+        total = 1;
+    }
+
+    println!("\nNodes searched: {}", total);
+    println!("Time: {} ms ({:.0} nodes/s)", elapsed.as_millis(), total as f64 / elapsed.as_secs_f64().max(1e-9));
+    return Ok(());
+}
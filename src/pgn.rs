@@ -0,0 +1,407 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::fen::{FenError, START_FEN};
+use crate::game_status::GameStatus;
+use crate::san;
+use crate::san::SanError;
+use crate::state::State;
+use crate::types::Move;
+
+/*
+Record of a game being played, kept alongside the State so it can be written out as PGN. Moves are
+stored in SAN, which needs the position before the move, so they have to be added as they are played
+*/
+pub struct GameRecord {
+    // Tag pairs in the order they are written. The Seven Tag Roster always comes first
+    pub tags: Vec<(String, String)>,
+    // SAN of every move played so far
+    pub moves: Vec<String>,
+    // Move number and side to move of the first move, for games that don't start from the normal position
+    first_move_number: u16,
+    black_moves_first: bool,
+    pub status: GameStatus,
+}
+
+impl GameRecord {
+    /*
+    Start recording a game from start_state. Non-standard starting positions get the SetUp and FEN tags
+    so other tools can rebuild them
+    */
+    pub fn new(start_state: &State, white: &str, black: &str) -> Self {
+        let mut tags: Vec<(String, String)> = vec![
+            (String::from("Event"), String::from("Casual game")),
+            (String::from("Site"), String::from("chess_bot")),
+            (String::from("Date"), today()),
+            (String::from("Round"), String::from("-")),
+            (String::from("White"), String::from(white)),
+            (String::from("Black"), String::from(black)),
+            (String::from("Result"), String::from("*")),
+        ];
+
+        let fen = start_state.to_fen();
+        if fen != START_FEN {
+            tags.push((String::from("SetUp"), String::from("1")));
+            tags.push((String::from("FEN"), fen));
+        }
+
+        return GameRecord {
+            tags,
+            moves: Vec::new(),
+            first_move_number: start_state.fullmove_number,
+            black_moves_first: !start_state.is_white_turn(),
+            status: GameStatus::Ongoing,
+        };
+    }
+
+    /*
+    Record action, about to be played in cur_state. Must be called before the move is applied.
+    Returns the SAN the move was recorded as
+    */
+    pub fn push(&mut self, cur_state: &State, action: &Move) -> &str {
+        self.moves.push(san::action_to_san(cur_state, action));
+        return self.moves.last().unwrap();
+    }
+
+    /* Record how the game ended, updating the Result tag */
+    pub fn finish(&mut self, status: GameStatus) {
+        self.status = status;
+        for (name, value) in self.tags.iter_mut() {
+            if name == "Result" {
+                *value = String::from(status.result());
+            }
+        }
+    }
+
+    /* Generate the PGN text for the game. Games still going get a "*" result */
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        for (name, value) in &self.tags {
+            // quotes and backslashes inside tag values have to be escaped
+            let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, escaped));
+        }
+        pgn.push('\n');
+
+        // build the movetext as tokens, then wrap it so no line goes over 80 characters
+        let mut tokens: Vec<String> = Vec::new();
+        let mut move_number = self.first_move_number;
+        let mut white_to_move = !self.black_moves_first;
+        for (i, san) in self.moves.iter().enumerate() {
+            if white_to_move {
+                tokens.push(format!("{}.", move_number));
+            }
+            else if i == 0 {
+                tokens.push(format!("{}...", move_number));
+            }
+            tokens.push(san.clone());
+
+            if !white_to_move {
+                move_number += 1;
+            }
+            white_to_move = !white_to_move;
+        }
+        tokens.push(String::from(self.status.result()));
+
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() > 80 {
+                pgn.push('\n');
+                line_length = 0;
+            }
+            if line_length > 0 {
+                pgn.push(' ');
+                line_length += 1;
+            }
+            line_length += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push('\n');
+
+        return pgn;
+    }
+
+    /* Write the PGN for the game to the file at path, replacing anything already there */
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        return fs::write(path, self.to_pgn());
+    }
+}
+
+/* Today's date in the PGN "YYYY.MM.DD" format, using UTC */
+fn today() -> String {
+    let seconds = match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(_) => { return String::from("????.??.??"); }
+    };
+
+    // convert days since 1970-01-01 to a calendar date, counting in 400 year cycles starting from March
+    let days = seconds / 86400 + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    return format!("{:04}.{:02}.{:02}", year, month, day);
+}
+
+/* A game read from a PGN file. Comments, NAGs and variations are dropped, only the main line is kept */
+#[derive(Clone, Debug, Default)]
+pub struct PgnGame {
+    // Tag pairs in the order they appeared
+    pub tags: Vec<(String, String)>,
+    // Main line moves in SAN, with move numbers removed
+    pub moves: Vec<String>,
+    // Game termination marker: "1-0", "0-1", "1/2-1/2" or "*"
+    pub result: String,
+}
+
+/* Reasons PGN text can fail to load */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PgnError {
+    // The file couldn't be read
+    Io(String),
+    // A tag pair that isn't of the form [Name "value"]
+    BadTag(String),
+    // A '{' comment or '(' variation that is never closed
+    Unterminated(char),
+    // The FEN tag doesn't hold a valid position
+    InvalidFen(FenError),
+    // A move that can't be played. move_number and white_move say exactly which one
+    IllegalMove { move_number: u16, white_move: bool, san: String, reason: SanError },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            PgnError::Io(err) => write!(f, "could not read file: {}", err),
+            PgnError::BadTag(tag) => write!(f, "malformed tag pair '{}'", tag),
+            PgnError::Unterminated(open) => write!(f, "'{}' is never closed", open),
+            PgnError::InvalidFen(err) => write!(f, "invalid FEN tag: {}", err),
+            PgnError::IllegalMove { move_number, white_move, san, reason } => {
+                let dots = if *white_move { "." } else { "..." };
+                write!(f, "move {}{} {} failed: {}", move_number, dots, san, reason)
+            }
+        };
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl PgnGame {
+    /* Returns the value of the tag called name, if the game has it */
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        return self.tags.iter().find(|(tag, _)| tag == name).map(|(_, value)| value.as_str());
+    }
+
+    /* The position the game starts from. The FEN tag is used if present, otherwise the normal start */
+    pub fn start_state(&self) -> Result<State, PgnError> {
+        return match self.tag("FEN") {
+            Some(fen) => State::from_fen(fen).map_err(PgnError::InvalidFen),
+            None => Ok(State::new()),
+        };
+    }
+
+    /*
+    Play the game's moves one by one through action_to_state. Returns the final state along with the
+    moves played, or the first move that couldn't be played
+    */
+    pub fn replay(&self) -> Result<(State, Vec<Move>), PgnError> {
+        let mut state = self.start_state()?;
+        let mut actions: Vec<Move> = Vec::new();
+
+        for san in &self.moves {
+            let action = san::parse_san(&state, san).map_err(|reason| PgnError::IllegalMove {
+                move_number: state.fullmove_number,
+                white_move: state.is_white_turn(),
+                san: san.clone(),
+                reason,
+            })?;
+            state.action_to_state(&action);
+            actions.push(action);
+        }
+
+        return Ok((state, actions));
+    }
+}
+
+/*
+Read every game in a PGN file. Tag pairs, comments ({} and ;), NAGs ($1) and variations (nested
+parentheses) are understood. Moves are only checked for legality when a game is replayed
+*/
+pub fn parse_pgn(text: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let mut games: Vec<PgnGame> = Vec::new();
+    let mut game: PgnGame = Default::default();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            // a tag after moves have been read means the previous game had no result marker
+            '[' => {
+                if !game.moves.is_empty() {
+                    game.result = String::from("*");
+                    games.push(std::mem::take(&mut game));
+                }
+                let end = find_closing(&chars, i, ']').ok_or(PgnError::Unterminated('['))?;
+                let tag: String = chars[i + 1..end].iter().collect();
+                game.tags.push(parse_tag(&tag)?);
+                i = end;
+            },
+            '{' => {
+                i = find_closing(&chars, i, '}').ok_or(PgnError::Unterminated('{'))?;
+            },
+            // rest of line comment
+            ';' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            },
+            // variations can nest, and can contain comments with parentheses in them
+            '(' => {
+                let mut depth = 0;
+                loop {
+                    if i >= chars.len() {
+                        return Err(PgnError::Unterminated('('));
+                    }
+                    match chars[i] {
+                        '(' => { depth += 1 },
+                        ')' => { depth -= 1 },
+                        '{' => { i = find_closing(&chars, i, '}').ok_or(PgnError::Unterminated('{'))? },
+                        _ => {}
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    i += 1;
+                }
+            },
+            // stray closing brackets are ignored
+            _ if c.is_whitespace() || ")]}".contains(c) => {},
+            _ => {
+                // everything else is a whitespace separated token
+                let start = i;
+                while i < chars.len() && !chars[i].is_whitespace() && !"[]{}();".contains(chars[i]) {
+                    i += 1;
+                }
+                let token: String = chars[start..i].iter().collect();
+                i -= 1;
+
+                match token.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                        game.result = token;
+                        games.push(std::mem::take(&mut game));
+                    },
+                    // NAGs are dropped
+                    _ if token.starts_with('$') => {},
+                    _ => {
+                        // move numbers can be stuck to the move, as in "1.e4" or "12...Nf6"
+                        let san = strip_move_number(&token);
+                        if !san.is_empty() {
+                            game.moves.push(String::from(san));
+                        }
+                    }
+                }
+            }
+        }
+        i += 1;
+    }
+
+    // a final game without a result marker is still kept
+    if !game.moves.is_empty() || !game.tags.is_empty() {
+        game.result = String::from("*");
+        games.push(game);
+    }
+
+    return Ok(games);
+}
+
+/* Read a PGN file from disk and parse every game in it */
+pub fn load_pgn(path: &str) -> Result<Vec<PgnGame>, PgnError> {
+    let text = fs::read_to_string(path).map_err(|err| PgnError::Io(err.to_string()))?;
+    return parse_pgn(&text);
+}
+
+/*
+Remove a move number from the start of token, so "12...Nf6" becomes "Nf6". A number only counts when dots
+follow it, so castling written with zeros, "0-0", is left alone
+*/
+fn strip_move_number(token: &str) -> &str {
+    let rest = token.trim_start_matches(|c: char| c.is_ascii_digit());
+    if !rest.starts_with('.') {
+        return token;
+    }
+    return rest.trim_start_matches('.');
+}
+
+/* Returns the index of the first close character after start, skipping over quoted strings */
+fn find_closing(chars: &[char], start: usize, close: char) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            // only tag values are quoted, and they can escape quotes with a backslash
+            '\\' if in_quotes => { i += 1 },
+            '"' if close == ']' => { in_quotes = !in_quotes },
+            c if c == close && !in_quotes => { return Some(i); },
+            _ => {}
+        }
+        i += 1;
+    }
+    return None;
+}
+
+/* Parse the inside of a tag pair, such as 'White "Kasparov, Garry"' */
+fn parse_tag(tag: &str) -> Result<(String, String), PgnError> {
+    let bad_tag = || PgnError::BadTag(String::from(tag));
+    let (name, value) = tag.trim().split_once(char::is_whitespace).ok_or_else(bad_tag)?;
+    let value = value.trim();
+    if !value.starts_with('"') || !value.ends_with('"') || value.len() < 2 {
+        return Err(bad_tag());
+    }
+    let unescaped = value[1..value.len() - 1].replace("\\\"", "\"").replace("\\\\", "\\");
+    return Ok((String::from(name), unescaped));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PieceKind, Square};
+
+    #[test]
+    fn castling_with_zeros_is_not_a_move_number() {
+        let games = parse_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 Nf6 5.0-0-0 *").unwrap();
+        assert_eq!(games[0].moves, ["e4", "e5", "Nf3", "Nc6", "Bc4", "Bc5", "0-0", "Nf6", "0-0-0"]);
+
+        let games = parse_pgn("1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4. 0-0 Nf6 *").unwrap();
+        let (state, moves) = games[0].replay().unwrap();
+        assert_eq!(moves.len(), 8);
+        assert_eq!(state.piece_at(Square::new(6, 0)).map(|piece| piece.kind), Some(PieceKind::King));
+    }
+
+    #[test]
+    fn skips_comments_nags_and_variations() {
+        let text = "[Event \"Test\"]\n\
+            1.e4 {best by test (really)} e5 $1 2. Nf3 (2. f4 exf4 (2... d5) 3. Nf3) 2... Nc6 ; rest of line\n\
+            3. Bb5 1-0";
+        let games = parse_pgn(text).unwrap();
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].tag("Event"), Some("Test"));
+        assert_eq!(games[0].moves, ["e4", "e5", "Nf3", "Nc6", "Bb5"]);
+        assert_eq!(games[0].result, "1-0");
+    }
+
+    #[test]
+    fn game_without_a_result_marker_is_unfinished() {
+        let games = parse_pgn("[Event \"a\"] 1. e4 e5 [Event \"b\"] 1. d4 *").unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].moves, ["e4", "e5"]);
+        assert_eq!(games[0].result, "*");
+        assert_eq!(games[1].tag("Event"), Some("b"));
+    }
+}
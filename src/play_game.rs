@@ -1,73 +1,166 @@
 
 use text_io;
 use regex::Regex;
-use std::collections::HashSet;
-use crate::minimax::MinimaxResult;
+use crate::minimax::{MinimaxResult, TimeControl};
+use crate::transposition::TranspositionTable;
 use crate::state;
+use crate::types::{Move, PieceKind, Square};
 use crate::minimax;
+use crate::pgn::GameRecord;
+use crate::san;
+use crate::san::SanError;
 use std::{thread, time, io::Write};
+use std::time::{Duration, Instant};
+
+/* Something the player can do on their turn */
+pub enum PlayerCommand {
+    Move(Move),
+    Resign,
+    // Print the PGN of the game so far
+    ShowPgn,
+}
+
 /*
- * Get an input from the player. This function ensures that the move the user gives as input is legal
+ * Get an input from the player. This function ensures that the move the user gives as input is legal.
+ * Moves can be given as coordinates ('xy to xy') or in SAN ('Nf3', 'exd5', 'O-O')
  */
-pub fn get_player_input(legal_moves: &HashSet<[i8; 4]>) -> [i8; 4] {
+pub fn get_player_input(cur_state: &state::State, legal_moves: &[Move]) -> PlayerCommand {
 
-    let re = Regex::new("[a-h][1-8] to [a-h][1-8]").unwrap();
     loop {
         print!("Input a move: ");
         // read until newline, not incluing
         let input: String = text_io::read!("{}\n");
+        let input = input.trim();
         let lower = input.to_lowercase();
-        // make sure input move is valid
-        if !re.is_match(&lower) {
-            println!("Invalid command format. Should to of form 'xy to xy'");
-            continue;
+        if lower == "resign" {
+            return PlayerCommand::Resign;
+        }
+        if lower == "pgn" {
+            return PlayerCommand::ShowPgn;
+        }
+
+        // anything not in coordinate form is read as SAN, which is case sensitive
+        let mut action = match translate_player_input(&lower) {
+            Some(action) => action,
+            None => {
+                match parse_player_san(cur_state, input) {
+                    Ok(action) => { return PlayerCommand::Move(action); },
+                    Err(err) => { println!("{}", san_error_message(cur_state, input, err)); },
+                }
+                continue;
+            }
+        };
+
+        // a pawn reaching the last row without a piece chosen becomes a queen
+        if action.promotion.is_none() && !legal_moves.contains(&action) {
+            let queen = Move { promotion: Some(PieceKind::Queen), ..action };
+            if legal_moves.contains(&queen) {
+                action = queen;
+            }
         }
 
-        let action = translate_player_input(&lower);
         if !legal_moves.contains(&action) {
-            println!("Illegal move. Try again.");
+            explain_illegal(cur_state, &action);
             continue;
         }
 
-        return action;
+        return PlayerCommand::Move(action);
     }
 }
 
-pub fn translate_player_input(input: &String) -> [i8; 4] {
+/* What to tell the player when input couldn't be read as a move, or wasn't legal */
+fn san_error_message(cur_state: &state::State, input: &str, err: SanError) -> String {
+    return match err {
+        SanError::Invalid(_) => {
+            String::from("Invalid command format. Should to of form 'xy to xy', 'xyxy' or 'xy-xy', adding '=p' or 'p' to promote a pawn, or a move in algebraic notation like 'Nf3'. Type 'resign' to give up, or 'pgn' to see the game so far")
+        },
+        // for castling the reason can be worked out from the side alone, wherever the king is
+        SanError::Illegal(_) if let Some(kingside) = castle_side(input) => {
+            format!("{}. Try again.", cur_state.diagnose_castle(kingside))
+        },
+        err => format!("{}. Try again.", err),
+    };
+}
 
-    let mut action: [i8; 4] = [0; 4];
-    let parts: Vec<char> = input.chars().collect();
+/*
+Which way the player is castling, true for the king side, if input is 'O-O' or 'O-O-O' written any of the
+ways the player can type it
+*/
+fn castle_side(input: &str) -> Option<bool> {
+    let castle = input.to_uppercase().replace('0', "O");
+    return match castle.trim_end_matches(['+', '#', '!', '?']) {
+        "O-O" => Some(true),
+        "O-O-O" => Some(false),
+        _ => None,
+    };
+}
 
-    // translation of coordinates to piece indices
-    action[0] = (parts[0] as i8) - 97;
+/* Tell the player why action can't be played */
+fn explain_illegal(cur_state: &state::State, action: &Move) {
+    match cur_state.diagnose_move(action) {
+        Err(reason) => println!("{}. Try again.", reason),
+        Ok(()) => println!("Illegal move. Try again."),
+    }
+}
 
-    // converting number character to actual number, then 
-    // converting to index
-    action[1] = (8 - (parts[1] as i8 - 48)).abs();
+/*
+Translate a lowercase move in coordinate form to an action. The squares can be separated by ' to ', a dash,
+a space or nothing at all, so 'e2 to e4', 'e2-e4', 'e2 e4' and the UCI style 'e2e4' all work. A promotion
+piece can follow, as in 'e7 to e8=n' or 'e7e8n'. Returns None if the input isn't in coordinate form
+*/
+pub fn translate_player_input(input: &str) -> Option<Move> {
 
-    action[2] = (parts[6] as i8) - 97;
-    action[3] = (8 - (parts[7] as i8 - 48)).abs();
+    let re = Regex::new("^([a-h][1-8])(?: to |-| )?([a-h][1-8])=?([qrbn])?$").unwrap();
+    let captures = re.captures(input)?;
 
+    let from = Square::from_name(&captures[1])?;
+    let to = Square::from_name(&captures[2])?;
+    let promotion = captures.get(3).and_then(|piece| piece.as_str().chars().next()).and_then(PieceKind::from_letter);
 
-    return action;
+    return Some(Move { from, to, promotion });
 }
 
 
 
-pub fn player_turn(cur_state: &state::State) -> [i8; 4] {
-    let legal_moves: HashSet<[i8; 4]> = cur_state.generate_legal_moves();
+/*
+Read a SAN move typed by the player. Piece letters other than 'b' are allowed in lowercase, since 'b'
+would clash with pawn moves on the b file, and castling can be typed with a lowercase 'o'
+*/
+fn parse_player_san(cur_state: &state::State, input: &str) -> Result<Move, SanError> {
+    let result = san::parse_san(cur_state, input);
+    if !matches!(result, Err(SanError::Invalid(_))) {
+        return result;
+    }
 
-    return get_player_input(&legal_moves);
+    let mut fixed: String = input.replace('o', "O");
+    if fixed.starts_with(['n', 'r', 'q', 'k']) {
+        fixed = fixed[..1].to_uppercase() + &fixed[1..];
+    }
+    return match san::parse_san(cur_state, &fixed) {
+        // it wasn't SAN either way, so report the error for what the player actually typed
+        Err(SanError::Invalid(_)) => result,
+        // otherwise the fixed move was readable, and being illegal or ambiguous is the real problem
+        fixed_result => fixed_result,
+    };
 }
 
-pub fn comp_turn(cur_state: &state::State) -> MinimaxResult {
-    match cur_state.is_white_turn() {
-        false => minimax::search_min(&cur_state, 5),
-        true => minimax::search_max(&cur_state, 5)
-    }
+pub fn player_turn(cur_state: &state::State) -> PlayerCommand {
+    let legal_moves: Vec<Move> = cur_state.generate_legal_moves();
+
+    return get_player_input(cur_state, &legal_moves);
 }
 
-pub fn play_game() {
+pub fn comp_turn(cur_state: &state::State, time_control: TimeControl, table: &mut TranspositionTable) -> MinimaxResult {
+    // only called while the game is going, so there's always a move
+    return minimax::search_timed(cur_state, time_control, table).expect("the computer has a legal move");
+}
+
+/*
+Play a game against the computer in the terminal. If pgn_path is given, the finished game is saved there as PGN.
+time_control sets how long the computer thinks. On a clock, both sides have the same time, and whoever runs
+out first loses
+*/
+pub fn play_game(pgn_path: Option<String>, time_control: TimeControl) {
     /* Create the initial state */
     let mut state = state::State::new();
 
@@ -91,39 +184,126 @@ pub fn play_game() {
         println!("Invalid input.")
     }
 
-    while state.victory_check() == 0 {
+    let mut record = match player {
+        true => GameRecord::new(&state, "Player", "chess_bot"),
+        false => GameRecord::new(&state, "chess_bot", "Player"),
+    };
+
+    // time left for each side, indexed by Color::index. Only kept when playing on a clock
+    let mut clocks: Option<[Duration; 2]> = match time_control {
+        TimeControl::Clock { remaining, .. } => Some([remaining; 2]),
+        TimeControl::FixedTime(_) => None,
+    };
+    let mut turn_start = Instant::now();
+
+    // the computer's transposition table, kept for the whole game
+    let mut table = TranspositionTable::default();
+
+    // keep playing until the game has been decided one way or another
+    while !state.status().is_over() {
+        let mover = state.side_to_move();
         if player == state.is_white_turn() {
-            println!("{}", state.to_string());
-            let action = player_turn(&mut state);
-            state.action_to_state(&action);
-            println!("{}", state.to_string());
+            println!("{}", state);
+            if let Some(clocks) = clocks {
+                println!("Clock: White {}, Black {}", format_clock(clocks[0]), format_clock(clocks[1]));
+            }
+            if state.in_check() {
+                println!("Check.");
+            }
+            match player_turn(&state) {
+                PlayerCommand::Move(action) => {
+                    record.push(&state, &action);
+                    state.action_to_state(&action);
+                },
+                PlayerCommand::Resign => state.resign(state.side_to_move()),
+                PlayerCommand::ShowPgn => {
+                    println!("{}", record.to_pgn());
+                    continue;
+                },
+            }
+            println!("{}", state);
         }
         else {
-            // need to give searcher thread own copy
+            // on a clock the engine plans around its own remaining time
+            let engine_time = match (time_control, clocks) {
+                (TimeControl::Clock { increment, .. }, Some(clocks)) => {
+                    TimeControl::Clock { remaining: clocks[mover.index()], increment }
+                },
+                _ => time_control,
+            };
+
+            // need to give searcher thread own copy. It borrows the table and hands it back when done
             let clone = state.clone();
+            let mut thread_table = table;
             let search_thread = thread::spawn(move || {
-                comp_turn(&clone)
+                let result = comp_turn(&clone, engine_time, &mut thread_table);
+                (result, thread_table)
             });
+            let animation = ['|', '/', '-', '\\'];
+            let mut frame = 0;
             while !search_thread.is_finished() {
-                let animation = ['|', '/', '-', '\\'];
-                for bar in animation {
-                    print!("\rThinking {bar}");
-                    thread::sleep(time::Duration::from_millis(250));
-                    std::io::stdout().flush();
-                }
+                print!("\rThinking {}", animation[frame % animation.len()]);
+                let _ = std::io::stdout().flush();
+                thread::sleep(time::Duration::from_millis(100));
+                frame += 1;
             }
-            std::io::stdout().flush();
-            let result = search_thread.join().unwrap();
+            let _ = std::io::stdout().flush();
+            let (result, returned_table) = search_thread.join().unwrap();
+            table = returned_table;
 
 
-            println!("\rMinimax value: {}", result.minimax_val);
+            println!("\rMinimax value: {} (depth {})", result.minimax_val, result.depth);
+            println!("Computer plays {}", record.push(&state, &result.action));
             state.action_to_state(&result.action);
         }
+
+        // charge the side that just moved for the time it took, and add the increment
+        if let (TimeControl::Clock { increment, .. }, Some(clocks)) = (time_control, &mut clocks) {
+            let used = turn_start.elapsed();
+            let left = &mut clocks[mover.index()];
+            if used > *left && !state.status().is_over() {
+                state.lose_on_time(mover);
+            }
+            *left = left.saturating_sub(used) + increment;
+        }
+        turn_start = Instant::now();
+    }
+    println!("{}", state);
+    println!("{}", state.status());
+
+    record.finish(state.status());
+    println!("\n{}", record.to_pgn());
+    if let Some(path) = pgn_path {
+        match record.save(&path) {
+            Ok(()) => println!("Game saved to {}", path),
+            Err(err) => println!("Could not save game to {}: {}", path, err),
+        }
     }
-    if state.victory_check() == 1 {
-        println!("White wins.")
+}
+
+/* Time on a clock as minutes and seconds, like 4:05 */
+fn format_clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    return format!("{}:{:02}", seconds / 60, seconds % 60);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lowercase_san_reports_illegal_moves() {
+        let state = state::State::new();
+        assert_eq!(parse_player_san(&state, "o-o").err(), Some(SanError::Illegal(String::from("O-O"))));
+        assert_eq!(parse_player_san(&state, "nf4").err(), Some(SanError::Illegal(String::from("Nf4"))));
+        assert_eq!(parse_player_san(&state, "zz9").err(), Some(SanError::Invalid(String::from("zz9"))));
+        assert!(parse_player_san(&state, "nf3").is_ok());
     }
-    else {
-        println!("Black wins.")
+    #[test]
+    fn lowercase_castling_is_explained() {
+        let state = state::State::from_fen("r3k2r/8/8/8/8/8/8/R4K1R w kq - 0 1").unwrap();
+        let err = parse_player_san(&state, "o-o").unwrap_err();
+        let reason = crate::illegal_move::IllegalMove::CastlingRightsLost { kingside: true };
+        assert_eq!(san_error_message(&state, "o-o", err), format!("{}. Try again.", reason));
     }
 }
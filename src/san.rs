@@ -0,0 +1,174 @@
+use std::fmt;
+use crate::game_status::GameStatus;
+use crate::state::State;
+use crate::types::{Move, Piece, PieceKind, Square};
+
+/*
+Generate the Standard Algebraic Notation for action, which must be legal in cur_state. Includes
+disambiguation when another piece of the same kind can reach the same square, and a '+' or '#' suffix
+for check and checkmate
+*/
+pub fn action_to_san(cur_state: &State, action: &Move) -> String {
+    let legal_moves: Vec<Move> = cur_state.generate_legal_moves();
+    let mut san = String::new();
+
+    let piece: Piece = cur_state.piece_at(action.from).expect("no piece on starting square");
+    let capture = cur_state.piece_at(action.to).is_some() ||
+        (piece.kind == PieceKind::Pawn && action.from.file() != action.to.file());
+
+    if piece.kind == PieceKind::King && action.from.file().abs_diff(action.to.file()) == 2 {
+        // castling is written by which side the king goes to
+        san.push_str(match action.to.file() > action.from.file() {
+            true => "O-O",
+            false => "O-O-O",
+        });
+    }
+    else if piece.kind == PieceKind::Pawn {
+        // pawn captures name the file the pawn came from
+        if capture {
+            san.push(action.from.file_char());
+            san.push('x');
+        }
+        san.push_str(&action.to.to_string());
+        if let Some(kind) = action.promotion {
+            san.push('=');
+            san.push(kind.letter());
+        }
+    }
+    else {
+        san.push(piece.kind.letter());
+
+        // other pieces of the same kind that could also move to the end square
+        let rivals: Vec<&Move> = legal_moves.iter().filter(|other| {
+            other.to == action.to && other.from != action.from && cur_state.piece_at(other.from) == Some(piece)
+        }).collect();
+
+        // the file is preferred, then the rank, then both if neither is enough on its own
+        if !rivals.is_empty() {
+            if rivals.iter().all(|other| other.from.file() != action.from.file()) {
+                san.push(action.from.file_char());
+            }
+            else if rivals.iter().all(|other| other.from.rank() != action.from.rank()) {
+                san.push(action.from.rank_char());
+            }
+            else {
+                san.push_str(&action.from.to_string());
+            }
+        }
+
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&action.to.to_string());
+    }
+
+    let mut new_state = cur_state.clone();
+    new_state.action_to_state(action);
+    if let GameStatus::Checkmate { .. } = new_state.status() {
+        san.push('#');
+    }
+    else if new_state.in_check() {
+        san.push('+');
+    }
+
+    return san;
+}
+
+/* Reasons a SAN move can fail to be read */
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SanError {
+    // The text isn't in SAN form at all
+    Invalid(String),
+    // The move is readable, but no legal move matches it
+    Illegal(String),
+    // More than one legal move matches, it needs a file or rank to tell them apart
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            SanError::Invalid(san) => write!(f, "'{}' is not a move in algebraic notation", san),
+            SanError::Illegal(san) => write!(f, "{} is not a legal move", san),
+            SanError::Ambiguous(san) => write!(f, "{} could be more than one move, add the starting file or rank", san),
+        };
+    }
+}
+
+impl std::error::Error for SanError {}
+
+/*
+Read a move in Standard Algebraic Notation and find the legal move in cur_state it describes. Check and
+annotation suffixes ('+', '#', '!', '?') are ignored, castling can be written with letter O or zero, and
+the capture 'x' is optional
+*/
+pub fn parse_san(cur_state: &State, text: &str) -> Result<Move, SanError> {
+    let san = text.trim_end_matches(['+', '#', '!', '?']);
+    let legal_moves: Vec<Move> = cur_state.generate_legal_moves();
+
+    // castling is found by which way the king moves two squares
+    if san == "O-O" || san == "0-0" || san == "O-O-O" || san == "0-0-0" {
+        let kingside = san.len() == 3;
+        let castle = legal_moves.iter().find(|action| {
+            cur_state.piece_at(action.from).is_some_and(|piece| piece.kind == PieceKind::King) &&
+            action.to.file() as i8 - action.from.file() as i8 == if kingside { 2 } else { -2 }
+        });
+        return castle.copied().ok_or(SanError::Illegal(String::from(text)));
+    }
+
+    let mut parts: Vec<char> = san.chars().collect();
+    let invalid = || SanError::Invalid(String::from(text));
+
+    // promotion piece comes last, either as "e8=Q" or "e8Q"
+    let mut promotion: Option<PieceKind> = None;
+    if parts.len() > 2 && "QRBNqrbn".contains(parts[parts.len() - 1]) && parts[parts.len() - 2].is_ascii_digit() {
+        promotion = PieceKind::from_letter(parts.pop().unwrap());
+    }
+    else if parts.len() > 2 && parts[parts.len() - 2] == '=' {
+        let letter = parts.pop().unwrap();
+        if !"QRBNqrbn".contains(letter) {
+            return Err(invalid());
+        }
+        promotion = PieceKind::from_letter(letter);
+        parts.pop();
+    }
+
+    // the destination square is always next
+    if parts.len() < 2 {
+        return Err(invalid());
+    }
+    let destination: String = parts.split_off(parts.len() - 2).into_iter().collect();
+    let to = Square::from_name(&destination).ok_or_else(invalid)?;
+
+    // a leading capital letter names the piece, no letter means a pawn
+    let mut kind = PieceKind::Pawn;
+    if !parts.is_empty() && "KQRBN".contains(parts[0]) {
+        kind = PieceKind::from_letter(parts.remove(0)).unwrap();
+    }
+
+    // anything left is the capture marker and the starting file and/or rank
+    let mut from_file: Option<u8> = None;
+    let mut from_rank: Option<u8> = None;
+    for part in parts {
+        match part {
+            'x' | ':' => {},
+            'a'..='h' if from_file.is_none() => { from_file = Some(part as u8 - b'a') },
+            '1'..='8' if from_rank.is_none() => { from_rank = Some(part as u8 - b'1') },
+            _ => { return Err(invalid()); }
+        }
+    }
+
+    let matching: Vec<&Move> = legal_moves.iter().filter(|action| {
+        cur_state.piece_at(action.from).is_some_and(|piece| piece.kind == kind) &&
+        action.to == to &&
+        action.promotion == promotion &&
+        from_file.is_none_or(|file| file == action.from.file()) &&
+        from_rank.is_none_or(|rank| rank == action.from.rank())
+    }).collect();
+
+    return match matching.len() {
+        0 => Err(SanError::Illegal(String::from(text))),
+        1 => Ok(*matching[0]),
+        _ => Err(SanError::Ambiguous(String::from(text))),
+    };
+}
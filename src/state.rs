@@ -1,60 +1,232 @@
-use std::collections::HashMap;
 use std::collections::HashSet;
+use std::fmt;
 use std::hash::{Hash, Hasher};
+use crate::bitboard::{self, Bitboard, square_bit, squares};
 use crate::legal_moves;
+use crate::zobrist;
+use crate::game_status::{DrawReason, GameStatus};
+use crate::types::{Color, Move, Piece, PieceKind, Square};
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct State {
-    // Dict mapping white piece location to piece
-    white: HashMap<(i8, i8), char>,
-    // Dict mapping black piece location to piece
-    black: HashMap<(i8, i8), char>,
-    // Boolean dictating which side's turn it is
-    white_turn: bool,
-    // -1 if black has taken out opposing king, 1 if white has, 0 if neither 
-    victory_flag: i8,
+    // Squares holding each kind of piece, indexed by Color::index then PieceKind::index
+    pub(crate) pieces: [[Bitboard; 6]; 2],
+    // Squares holding any piece of each colour, indexed by Color::index
+    pub(crate) occupancy: [Bitboard; 2],
+    // Which side's turn it is
+    pub(crate) turn: Color,
+    // Which castling moves are still available to each side
+    pub(crate) castling: CastlingRights,
+    // Square skipped over by a pawn that moved two spaces last turn. An opposing pawn can capture onto it
+    pub(crate) en_passant: Option<Square>,
+    // Zobrist key of the position, kept up to date as pieces are moved. See zobrist.rs
+    pub(crate) key: u64,
+    // Number of moves, counting both sides, since the last capture or pawn move. Used for the fifty-move rule
+    pub(crate) halfmove_clock: u16,
+    // Keys of every position reached in the game, including the current one. Only the last halfmove_clock
+    // of them can be repeated, positions from before a capture or pawn move can never come up again
+    pub(crate) history: Vec<u64>,
+    // Number of the current full move. Starts at 1 and goes up after each black move
+    pub(crate) fullmove_number: u16,
+    // Set when the game has ended by resignation or on time, which can't be worked out from the board
+    pub(crate) ended: Option<GameStatus>,
 }
 
-impl State {
+/*
+What make_move changed that can't be worked out again from the move, so unmake_move can put it back
+*/
+#[derive(Clone, Copy, Debug)]
+pub struct Undo {
+    // The piece taken and the square it was on, which differs from the move's end square for en passant
+    captured: Option<(Piece, Square)>,
+    castling: CastlingRights,
+    en_passant: Option<Square>,
+    halfmove_clock: u16,
+    key: u64,
+}
 
+/*
+Castling rights for both sides. A right is lost for good once the king or the matching rook moves,
+or the rook is captured
+*/
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool,
+}
 
-    /*
-    Generate a string representation of the current state
-    */
-    pub fn to_string(&self) -> String {
+impl CastlingRights {
+    /* Both sides can castle either way */
+    pub fn all() -> Self {
+        return CastlingRights {
+            white_kingside: true,
+            white_queenside: true,
+            black_kingside: true,
+            black_queenside: true,
+        };
+    }
+
+    /* Returns side's king side and queen side rights */
+    pub fn for_side(&self, side: Color) -> (bool, bool) {
+        return match side {
+            Color::White => (self.white_kingside, self.white_queenside),
+            Color::Black => (self.black_kingside, self.black_queenside),
+        };
+    }
+
+    /* The four rights packed into the low bits of a number, used to index tables */
+    pub fn bits(&self) -> usize {
+        return self.white_kingside as usize | (self.white_queenside as usize) << 1 |
+            (self.black_kingside as usize) << 2 | (self.black_queenside as usize) << 3;
+    }
+
+    /* Remove any right tied to a rook's starting corner, used when a piece moves from or to that square */
+    fn clear_corner(&mut self, square: Square) {
+        match (square.file(), square.rank()) {
+            (7, 0) => { self.white_kingside = false },
+            (0, 0) => { self.white_queenside = false },
+            (7, 7) => { self.black_kingside = false },
+            (0, 7) => { self.black_queenside = false },
+            _ => {}
+        }
+    }
+}
+
+/*
+Generate a string representation of the current state
+*/
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut str_rep: String = String::from("   A  B  C  D  E  F  G  H \n");
-        for i in 0..8 {
-            // y axis coordinates are 1-8 from bottom to top
-            str_rep.push_str(&format!("{} ", i8::abs(i-8)));
-            for j in 0..8 {
-
-                if self.white.contains_key(&(j,i)) {
-                    let piece: char = *self.white.get(&(j,i)).expect("Not in hashmap");
-                    str_rep.push_str(&format!(" {} ", piece));
-                }
-                else if self.black.contains_key(&(j,i)) {
-                    let piece: char = *self.black.get(&(j,i)).expect("Not in hashmap");
-                    str_rep.push_str(&format!(" {} ", piece));
-                }
-                else {
-                    str_rep.push_str(" . ");
+        // ranks are printed 8 to 1 from top to bottom
+        for rank in (0..8).rev() {
+            str_rep.push_str(&format!("{} ", rank + 1));
+            for file in 0..8 {
+                match self.piece_at(Square::new(file, rank)) {
+                    Some(piece) => str_rep.push_str(&format!(" {} ", piece)),
+                    None => str_rep.push_str(" . "),
                 }
             }
-            str_rep.push_str(&format!(" {}\n", i8::abs(i-8)));
+            str_rep.push_str(&format!(" {}\n", rank + 1));
         }
         str_rep.push_str("   A  B  C  D  E  F  G  H \n");
-    return str_rep;
+        return write!(f, "{}", str_rep);
     }
+}
 
+impl State {
+
+    /* Returns the piece on square, if there is one */
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        let bit = square_bit(square);
+        let color = match bit {
+            _ if self.occupancy[Color::White.index()] & bit != 0 => Color::White,
+            _ if self.occupancy[Color::Black.index()] & bit != 0 => Color::Black,
+            _ => { return None; }
+        };
+        let kind = PieceKind::ALL.into_iter().find(|kind| self.pieces[color.index()][kind.index()] & bit != 0)?;
+        return Some(Piece::new(kind, color));
+    }
+
+    /* Returns the squares holding color's pieces of kind */
+    pub fn pieces(&self, color: Color, kind: PieceKind) -> Bitboard {
+        return self.pieces[color.index()][kind.index()];
+    }
+
+    /* Returns the squares holding any of color's pieces */
+    pub fn occupancy(&self, color: Color) -> Bitboard {
+        return self.occupancy[color.index()];
+    }
+
+    /* Returns every occupied square on the board */
+    pub fn occupied(&self) -> Bitboard {
+        return self.occupancy[0] | self.occupancy[1];
+    }
+
+    /* Returns the square color's king stands on */
+    pub fn king_square(&self, color: Color) -> Square {
+        return Square::from_index(self.pieces(color, PieceKind::King).trailing_zeros() as u8);
+    }
+
+    /* Place piece on square, which must be empty */
+    pub(crate) fn put_piece(&mut self, square: Square, piece: Piece) {
+        let bit = square_bit(square);
+        self.pieces[piece.color.index()][piece.kind.index()] |= bit;
+        self.occupancy[piece.color.index()] |= bit;
+        self.key ^= zobrist::piece(piece, square);
+    }
+
+    /* Take whatever piece is on square off the board, returning it */
+    pub(crate) fn remove_piece(&mut self, square: Square) -> Option<Piece> {
+        let piece = self.piece_at(square)?;
+        let bit = square_bit(square);
+        self.pieces[piece.color.index()][piece.kind.index()] &= !bit;
+        self.occupancy[piece.color.index()] &= !bit;
+        self.key ^= zobrist::piece(piece, square);
+        return Some(piece);
+    }
+
+    /* Returns the castling moves still available to both sides */
+    pub fn castling_rights(&self) -> CastlingRights {
+        return self.castling;
+    }
+
+    /* Returns the square a pawn can currently capture onto en passant, if any */
+    pub fn en_passant_square(&self) -> Option<Square> {
+        return self.en_passant;
+    }
 
     /* Returns whether or not it's white's turn */
     pub fn is_white_turn(&self) -> bool {
-        return self.white_turn;
+        return self.turn == Color::White;
+    }
+
+    /* Returns the side whose turn it is */
+    pub fn side_to_move(&self) -> Color {
+        return self.turn;
     }
 
-    /* Check if either player has won. Used in minimax search */
-    pub fn victory_check(&self) -> i8 {
-        return self.victory_flag;
+    /* Returns how the game stands in this state. Generates the legal moves to look for mate and stalemate */
+    pub fn status(&self) -> GameStatus {
+        return self.status_from_moves(&self.generate_legal_moves());
+    }
+
+    /*
+    Same as status, but reuses legal moves the caller has already generated for this state.
+    Used by the minimax search, which needs the moves anyway
+    */
+    pub fn status_from_moves(&self, legal_moves: &[Move]) -> GameStatus {
+        if let Some(status) = self.ended {
+            return status;
+        }
+        if legal_moves.is_empty() {
+            // the side to move is the one that has been mated
+            if self.in_check() {
+                return GameStatus::Checkmate { winner: self.side_to_move().opponent() };
+            }
+            return GameStatus::Stalemate;
+        }
+        if let Some(reason) = self.draw_reason() {
+            return GameStatus::Draw(reason);
+        }
+        return GameStatus::Ongoing;
+    }
+
+    /* Record that side has resigned, ending the game */
+    pub fn resign(&mut self, side: Color) {
+        self.ended = Some(GameStatus::Resignation { winner: side.opponent() });
+    }
+
+    /* Record that side has run out of time, ending the game */
+    pub fn lose_on_time(&mut self, side: Color) {
+        self.ended = Some(GameStatus::Timeout { winner: side.opponent() });
+    }
+
+    /* Returns whether the king of the side to move is currently attacked */
+    pub fn in_check(&self) -> bool {
+        return self.is_in_check(self.turn);
     }
 
     /*
@@ -62,221 +234,377 @@ impl State {
     */
     pub fn new() -> Self {
 
-        let mut new_state: State = Default::default();
-        new_state.black =  HashMap::from([
-            ((0,0), '♜'),
-            ((1,0), '♞'),
-            ((2,0), '♝'),
-            ((3,0), '♛'),
-            ((4,0), '♚'),
-            ((5,0), '♝'),
-            ((6,0), '♞'),
-            ((7,0), '♜'),
-        ]);
-        for i in 0..8 {
-            new_state.black.insert((i, 1), '♟');
-        }
+        let mut new_state = State {
+            turn: Color::White,
+            castling: CastlingRights::all(),
+            fullmove_number: 1,
+            ..Default::default()
+        };
 
-        new_state.white = HashMap::from([
-            ((0,7), '♖'),
-            ((1,7), '♘'),
-            ((2,7), '♗'),
-            ((3,7), '♕'),
-            ((4,7), '♔'),
-            ((5,7), '♗'),
-            ((6,7), '♘'),
-            ((7,7), '♖'),
-        ]);
-        for i in 0..8 {
-            new_state.white.insert((i, 6), '♙');
+        let back_row = [PieceKind::Rook, PieceKind::Knight, PieceKind::Bishop, PieceKind::Queen,
+            PieceKind::King, PieceKind::Bishop, PieceKind::Knight, PieceKind::Rook];
+        for (file, kind) in back_row.into_iter().enumerate() {
+            let file = file as u8;
+            new_state.put_piece(Square::new(file, 0), Piece::new(kind, Color::White));
+            new_state.put_piece(Square::new(file, 1), Piece::new(PieceKind::Pawn, Color::White));
+            new_state.put_piece(Square::new(file, 7), Piece::new(kind, Color::Black));
+            new_state.put_piece(Square::new(file, 6), Piece::new(PieceKind::Pawn, Color::Black));
         }
 
-        new_state.white_turn = true;
-        new_state.victory_flag = 0;
-        //new_state.white_turn = false;
+        new_state.key = new_state.compute_key();
+        new_state.history.push(new_state.key);
         return new_state;
 
     }
 
-    pub fn estimate_minimax(&self) -> i16 {
-        let mut minimax_val: i16 = 0;
-        for (&_key, value) in &self.white {
-            match value {
-                '♕' => { minimax_val += 50},
-                '♔' => { minimax_val += 100 },
-                '♖' => { minimax_val += 15 },
-                '♗' => { minimax_val += 10 },
-                '♘' => { minimax_val += 7 },
-                '♙' => { minimax_val += 1 },
-                _   => { () }
-            }
-        }  
-        for (&_key, value) in &self.black {
-            match value {
-                '♛' => { minimax_val -= 50},
-                '♚' => { minimax_val -= 100 },
-                '♜' => { minimax_val -= 15 },
-                '♝' => { minimax_val -= 10 },
-                '♞' => { minimax_val -= 7 },
-                '♟' => { minimax_val -= 1 },
-                _   => { () }
-            }
-        }  
-        return minimax_val;
+    /*
+    Estimate how good the position is for the side to move, by counting material. Positive is better for
+    the side to move, negative for their opponent
+    */
+    pub fn evaluate(&self) -> i16 {
+        let mut value: i16 = 0;
+        for kind in PieceKind::ALL {
+            let own = self.pieces(self.turn, kind).count_ones() as i16;
+            let opp = self.pieces(self.turn.opponent(), kind).count_ones() as i16;
+            value += (own - opp) * State::piece_value(kind);
+        }
+        return value;
     }
 
+    /* Material value of a piece, used by evaluate */
+    pub fn piece_value(kind: PieceKind) -> i16 {
+        return match kind {
+            PieceKind::Queen => 50,
+            PieceKind::King => 100,
+            PieceKind::Rook => 15,
+            PieceKind::Bishop => 10,
+            PieceKind::Knight => 7,
+            PieceKind::Pawn => 1,
+        };
+    }
 
-    pub fn action_to_state(&mut self, action: &[i8; 4]) {
 
-        // get piece positions from action array
-        let start_x = action[0];
-        let start_y = action[1];
-        let end_x = action[2];
-        let end_y = action[3];
+    /* Apply action to the state. Same as make_move, for callers that don't need to take the move back */
+    pub fn action_to_state(&mut self, action: &Move) {
+        self.make_move(action);
+    }
 
-        // get the current player's hashmap. Mutable borrow, so done in block
-        {
-            let cur_player: &mut HashMap<(i8, i8), char> = match self.white_turn {
-                true => { &mut self.white },
-                false => { &mut self.black },
-            };
+    /*
+    Apply action, which must be legal, to the state. Returns what's needed for unmake_move to restore the
+    state exactly as it was
+    */
+    pub fn make_move(&mut self, action: &Move) -> Undo {
+
+        let from = action.from;
+        let to = action.to;
+        let mut undo = Undo {
+            captured: None,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            key: self.key,
+        };
+
+        // the castling rights and en passant square are about to change, take them out of the key
+        self.key ^= zobrist::castling(self.castling) ^ self.en_passant_key();
+
+        // move piece from starting position to ending position, taking anything already there
+        let piece: Piece = self.remove_piece(from).expect("no piece on starting square");
+        undo.captured = self.remove_piece(to).map(|captured| (captured, to));
 
-            // move piece from starting position to ending position
-            let piece: char = cur_player.remove(&(start_x, start_y)).expect("piece not in hashmap");
-            cur_player.insert((end_x, end_y), piece);
+        // a promoting pawn is replaced by the chosen piece in the current player's colour
+        match action.promotion {
+            Some(kind) => self.put_piece(to, Piece::new(kind, piece.color)),
+            None => self.put_piece(to, piece),
         }
 
-        // second block, check if opposing player has piece in end_pos. Remove if so 
-        {
-            let opp_player: &mut HashMap<(i8, i8), char> = match self.white_turn {
-                false => { &mut self.white },
-                true => { &mut self.black },
-            };
-            //remove if possible
-            let opp_piece = opp_player.remove(&(end_x, end_y));
-            match opp_piece {
-                Some(x) => {
-                    /* If a piece did exist at this spot, check if it was a king and set state's victory flag accordingly */
-                    self.victory_flag = match x {
-                        // white has taken out opposing king
-                        '♚' => 1,
-                        // black has taken out opposing king
-                        '♔' => -1,
-                        // neither has taken out a king, victory flag remains unset
-                        _ => 0
-                    }
+        if piece.kind == PieceKind::King {
+            // a king moving two spaces is castling, the rook jumps to the other side of the king
+            let rank = from.rank();
+            if to.file() == from.file() + 2 {
+                let rook: Piece = self.remove_piece(Square::new(7, rank)).expect("no rook to castle with");
+                self.put_piece(Square::new(5, rank), rook);
+            }
+            else if to.file() + 2 == from.file() {
+                let rook: Piece = self.remove_piece(Square::new(0, rank)).expect("no rook to castle with");
+                self.put_piece(Square::new(3, rank), rook);
+            }
+
+            // moving the king gives up both castling rights
+            match self.turn {
+                Color::White => {
+                    self.castling.white_kingside = false;
+                    self.castling.white_queenside = false;
+                }
+                Color::Black => {
+                    self.castling.black_kingside = false;
+                    self.castling.black_queenside = false;
                 }
-                // wasn't a king, do nothing
-                _ => (),
             }
         }
+
+        // a rook leaving its corner, or being captured on it, loses that side's castling right
+        self.castling.clear_corner(from);
+        self.castling.clear_corner(to);
+
+        // a pawn moving diagonally onto the en passant square captures the pawn that passed it
+        if piece.kind == PieceKind::Pawn && from.file() != to.file() && self.en_passant == Some(to) {
+            let square = Square::new(to.file(), from.rank());
+            undo.captured = self.remove_piece(square).map(|captured| (captured, square));
+        }
+
+        // captures and pawn moves can't be undone, so earlier positions can't come up again
+        if undo.captured.is_some() || piece.kind == PieceKind::Pawn {
+            self.halfmove_clock = 0;
+        }
+        else {
+            self.halfmove_clock += 1;
+        }
+
+        // a pawn moving up/down two spaces can be taken en passant on the square it skipped, for one turn only
+        self.en_passant = match piece.kind {
+            PieceKind::Pawn if from.rank().abs_diff(to.rank()) == 2 => {
+                Some(Square::new(from.file(), (from.rank() + to.rank()) / 2))
+            }
+            _ => None,
+        };
+
+        if self.turn == Color::Black {
+            self.fullmove_number += 1;
+        }
+
         // swap who's turn it is
-        self.white_turn = !self.white_turn;
+        self.turn = self.turn.opponent();
+
+        self.key ^= zobrist::castling(self.castling) ^ self.en_passant_key() ^ zobrist::black_to_move();
+        self.history.push(self.key);
+
+        return undo;
+    }
+
+    /* Take back action, which must be the last move made, using the undo information make_move returned */
+    pub fn unmake_move(&mut self, action: &Move, undo: Undo) {
+        let from = action.from;
+        let to = action.to;
+
+        // it's the turn of the player who made the move again
+        self.turn = self.turn.opponent();
+        if self.turn == Color::Black {
+            self.fullmove_number -= 1;
+        }
+        self.history.pop();
+
+        // a promoted piece goes back to being a pawn
+        let mut piece: Piece = self.remove_piece(to).expect("no piece on end square");
+        if action.promotion.is_some() {
+            piece = Piece::new(PieceKind::Pawn, piece.color);
+        }
+        self.put_piece(from, piece);
 
+        if let Some((captured, square)) = undo.captured {
+            self.put_piece(square, captured);
+        }
+
+        // a castling rook goes back to its corner
+        if piece.kind == PieceKind::King {
+            let rank = from.rank();
+            if to.file() == from.file() + 2 {
+                let rook: Piece = self.remove_piece(Square::new(5, rank)).expect("no castled rook");
+                self.put_piece(Square::new(7, rank), rook);
+            }
+            else if to.file() + 2 == from.file() {
+                let rook: Piece = self.remove_piece(Square::new(3, rank)).expect("no castled rook");
+                self.put_piece(Square::new(0, rank), rook);
+            }
+        }
+
+        self.castling = undo.castling;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.key = undo.key;
     }
 
     /*
-    Creates a copy of this state
+    Zobrist key identifying the current position, used for repetition checks and the transposition table.
+    Two positions with the same pieces, side to move, castling rights and en passant captures get the same key
     */
-    pub fn clone(&self) -> Self {
-        let mut new_state: State = State::new();
-        new_state.white = self.white.clone();
-        new_state.black = self.black.clone();
-        new_state.victory_flag = self.victory_flag;
-        new_state.white_turn = self.white_turn;
+    pub fn zobrist_key(&self) -> u64 {
+        return self.key;
+    }
 
-        return new_state;
+    /* Work out the Zobrist key from scratch. Used when a state is set up, after that it's kept up to date */
+    pub(crate) fn compute_key(&self) -> u64 {
+        let mut key: u64 = zobrist::castling(self.castling) ^ self.en_passant_key();
+        if self.turn == Color::Black {
+            key ^= zobrist::black_to_move();
+        }
+        for color in [Color::White, Color::Black] {
+            for kind in PieceKind::ALL {
+                for square in squares(self.pieces(color, kind)) {
+                    key ^= zobrist::piece(Piece::new(kind, color), square);
+                }
+            }
+        }
+        return key;
     }
+
     /*
-    Generate a list of legal moves that can be applied to the current state
+    Key for the en passant square. It's only counted when a pawn of the side to move can actually capture
+    onto it, since otherwise the position is the same as if there were no en passant square
     */
-    pub fn generate_legal_moves(&self) -> HashSet<[i8; 4]> {
+    fn en_passant_key(&self) -> u64 {
+        return match self.en_passant {
+            Some(square) if bitboard::pawn_attacks(self.turn.opponent(), square) & self.pieces(self.turn, PieceKind::Pawn) != 0 => {
+                zobrist::en_passant(square)
+            },
+            _ => 0,
+        };
+    }
+
+    /* Returns the reason the game is drawn in the current position, if any. Stalemate is checked separately */
+    pub fn draw_reason(&self) -> Option<DrawReason> {
+        if self.insufficient_material() {
+            return Some(DrawReason::InsufficientMaterial);
+        }
+        // one hundred moves from both sides together is fifty full moves
+        if self.halfmove_clock >= 100 {
+            return Some(DrawReason::FiftyMoveRule);
+        }
+        // only positions since the last capture or pawn move can repeat
+        let reversible = &self.history[self.history.len().saturating_sub(self.halfmove_clock as usize + 1)..];
+        let current = reversible.last();
+        if reversible.iter().filter(|key| Some(*key) == current).count() >= 3 {
+            return Some(DrawReason::ThreefoldRepetition);
+        }
+        return None;
+    }
 
-        let mut legal_moves: HashSet<[i8; 4]> = HashSet::new();
+    /*
+    Neither side can possibly checkmate. This is the case for king against king, king and one minor piece
+    against king, or when the only other pieces are bishops that all stand on the same colour square
+    */
+    fn insufficient_material(&self) -> bool {
+        let both = |kind: PieceKind| self.pieces(Color::White, kind) | self.pieces(Color::Black, kind);
 
-        /* Get the piece positions for the current player and the opposing player */
-        let cur_player = if self.white_turn {
-            &self.white
-        } else {
-            &self.black
-        };
+        // pawns, rooks and queens can always force mate with enough help
+        if both(PieceKind::Pawn) | both(PieceKind::Rook) | both(PieceKind::Queen) != 0 {
+            return false;
+        }
 
-        let opp_player = if self.white_turn {
-            &self.black
-        } else {
-            &self.white
-        };
+        let bishops = both(PieceKind::Bishop);
+        let minor_pieces = (bishops | both(PieceKind::Knight)).count_ones();
+        let bishop_colours: HashSet<bool> = squares(bishops).map(|square| square.is_light()).collect();
+        return minor_pieces <= 1 || (both(PieceKind::Knight) == 0 && bishop_colours.len() == 1);
+    }
 
-        for (&key, value) in cur_player {
+    /*
+    Generate a list of legal moves that can be applied to the current state. Moves that would leave
+    the current player's king attacked are filtered out
+    */
+    pub fn generate_legal_moves(&self) -> Vec<Move> {
+        let mut legal_moves: Vec<Move> = self.generate_pseudo_legal_moves();
+
+        // out of check, only king moves, pinned pieces and en passant (which takes two pieces off the same
+        // rank) can expose the king. Everything else is legal without playing it out
+        let in_check = self.checkers() != 0;
+        let king = self.king_square(self.turn);
+        let pinned = self.pinned(self.turn);
+        let pawns = self.pieces(self.turn, PieceKind::Pawn);
+        legal_moves.retain(|action| {
+            let risky = in_check || action.from == king || pinned & square_bit(action.from) != 0 ||
+                (self.en_passant == Some(action.to) && pawns & square_bit(action.from) != 0);
+            !risky || !self.leaves_king_attacked(action)
+        });
+        return legal_moves;
+    }
 
-            let loc_x = key.0;
-            let loc_y = key.1;
+    /*
+    Check whether playing action would leave the current player's king attacked. Only the piece boards are
+    updated, which is all that's needed to see the attacks. Castling rook moves can't expose the king,
+    so they're left out
+    */
+    fn leaves_king_attacked(&self, action: &Move) -> bool {
+        let opp = self.turn.opponent();
+        let mut own: [Bitboard; 6] = self.pieces[self.turn.index()];
+        let mut attacker: [Bitboard; 6] = self.pieces[opp.index()];
+
+        let from = square_bit(action.from);
+        let to = square_bit(action.to);
+        let kind = PieceKind::ALL.into_iter().find(|kind| own[kind.index()] & from != 0).expect("no piece on starting square");
+        own[kind.index()] ^= from | to;
+        for board in attacker.iter_mut() {
+            *board &= !to;
+        }
 
-            // put all legal moves for current board state in legal_moves vector
-            match value {
-                '♛' | '♕' => {
-                    legal_moves::queen_legal_moves(loc_x, loc_y, &cur_player, &opp_player, &mut legal_moves);
-                }
-                '♚' | '♔' => {
-                    legal_moves::king_legal_moves(loc_x, loc_y, &cur_player, &mut legal_moves);
-                },
-                '♜' | '♖' => {
-                    legal_moves::rook_legal_moves(loc_x, loc_y, &cur_player, &opp_player, &mut legal_moves);
-                },
-                '♝' | '♗' => {
-                    legal_moves::bishop_legal_moves(loc_x, loc_y, &cur_player, &opp_player, &mut legal_moves);
-                },
-                '♞' | '♘' => {
-                    legal_moves::knight_legal_moves(loc_x, loc_y, &cur_player, &mut legal_moves);
-                }
-                '♟' | '♙' => {
-                    legal_moves::pawn_legal_moves(self.white_turn, loc_x, loc_y, &cur_player, &opp_player, &mut legal_moves);
-                }
-                _ => ()
-            }
+        // the pawn taken en passant isn't on the square moved to
+        if kind == PieceKind::Pawn && action.from.file() != action.to.file() && self.en_passant == Some(action.to) {
+            attacker[PieceKind::Pawn.index()] &= !square_bit(Square::new(action.to.file(), action.from.rank()));
         }
 
-            return legal_moves;
+        let occupied: Bitboard = own.iter().chain(attacker.iter()).fold(0, |all, board| all | board);
+        let king = Square::from_index(own[PieceKind::King.index()].trailing_zeros() as u8);
+        return legal_moves::square_attacked(king, opp, &attacker, occupied);
     }
 
+    /*
+    Generate every move the current player's pieces can make, without checking if the move
+    leaves their own king in check
+    */
+    fn generate_pseudo_legal_moves(&self) -> Vec<Move> {
+
+        let mut legal_moves: Vec<Move> = Vec::with_capacity(64);
 
-}
+        /* Get the piece positions for the current player and the opposing player */
+        let own: Bitboard = self.occupancy(self.turn);
+        let opp: Bitboard = self.occupancy(self.turn.opponent());
 
-impl Clone for State {
-    fn clone(&self) -> Self {
-        let mut new_state: State = State::new();
-        new_state.white = self.white.clone();
-        new_state.black = self.black.clone();
-        new_state.victory_flag = self.victory_flag;
-        new_state.white_turn = self.white_turn;
+        for kind in PieceKind::ALL {
+            for square in squares(self.pieces(self.turn, kind)) {
 
-        return new_state;
+                // put all legal moves for current board state in legal_moves vector
+                match kind {
+                    PieceKind::Queen => {
+                        legal_moves::queen_legal_moves(square, own, opp, &mut legal_moves);
+                    }
+                    PieceKind::King => {
+                        legal_moves::king_legal_moves(square, own, &mut legal_moves);
+
+                        let (kingside, queenside) = self.castling.for_side(self.turn);
+                        legal_moves::castling_legal_moves(self.turn, kingside, queenside, &self.pieces[self.turn.index()],
+                            &self.pieces[self.turn.opponent().index()], &mut legal_moves);
+                    },
+                    PieceKind::Rook => {
+                        legal_moves::rook_legal_moves(square, own, opp, &mut legal_moves);
+                    },
+                    PieceKind::Bishop => {
+                        legal_moves::bishop_legal_moves(square, own, opp, &mut legal_moves);
+                    },
+                    PieceKind::Knight => {
+                        legal_moves::knight_legal_moves(square, own, &mut legal_moves);
+                    }
+                    PieceKind::Pawn => {
+                        legal_moves::pawn_legal_moves(self.turn, square, own, opp, self.en_passant, &mut legal_moves);
+                    }
+                }
+            }
+        }
+
+        return legal_moves;
     }
+
+
 }
 
 impl Hash for State {
     fn hash<H: Hasher>(&self, s: &mut H) {
-        let mut items1: Vec<_> = self.white.iter().collect();
-        items1.sort_by_key(|(k,_)| **k);
-        for (k,v) in items1 {
-            k.hash(s);
-            v.hash(s);
-        }
-
-        let mut items2: Vec<_> = self.black.iter().collect();
-        items2.sort_by_key(|(k,_)| **k);
-        for (k,v) in items2 {
-            k.hash(s);
-            v.hash(s);
-        }
+        self.key.hash(s);
     }
 }
 
 impl PartialEq for State {
     fn eq(&self, other: &Self) -> bool {
-        self.white == other.white && self.black == other.black
+        self.key == other.key && self.pieces == other.pieces && self.turn == other.turn &&
+        self.castling == other.castling && self.en_passant_key() == other.en_passant_key()
     }
 }
-impl Eq for State {}
-
-
+impl Eq for State {}
\ No newline at end of file
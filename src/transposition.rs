@@ -0,0 +1,140 @@
+use crate::types::Move;
+
+// Size of the table the game is played with, in megabytes
+pub const DEFAULT_SIZE_MB: usize = 32;
+
+/*
+How a stored score relates to the position's real score. Alpha-beta only finds the exact score when it lands
+inside the window. A move reaching beta stops the search early, so the real score is at least that much, and
+when nothing reaches alpha the real score is at most the best found
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+/* What a search learned about one position */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Entry {
+    // Full Zobrist key, since many positions share a slot
+    pub key: u64,
+    // How many moves ahead the position was searched
+    pub depth: u16,
+    pub bound: Bound,
+    pub score: i16,
+    // The move that scored best or caused the cutoff, none if every move failed to reach alpha
+    pub best_move: Option<Move>,
+    // Which search stored the entry
+    generation: u8,
+}
+
+/*
+Fixed size hash table of searched positions, indexed by Zobrist key. It's kept between moves, since most of
+what was searched for the last move is still relevant to the next
+*/
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    // Bumped for every new search, so entries left over from earlier moves are replaced first
+    generation: u8,
+}
+
+impl TranspositionTable {
+    /* Table taking up roughly size_mb megabytes. The number of entries is rounded down to a power of two */
+    pub fn new(size_mb: usize) -> Self {
+        let wanted = (size_mb * 1024 * 1024 / size_of::<Option<Entry>>()).max(1);
+        let len = 1 << wanted.ilog2();
+        return TranspositionTable { entries: vec![None; len], generation: 0 };
+    }
+
+    /* Call before searching for a new move, so the entries from earlier moves age */
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /* The entry stored for key, if its slot hasn't been taken by another position since */
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        return self.entries[self.index(key)].filter(|entry| entry.key == key);
+    }
+
+    /*
+    Store what was found for key. The slot's current entry is kept only if it's from this search, belongs to
+    another position and was searched deeper, since that one saved more work
+    */
+    pub fn store(&mut self, key: u64, depth: u16, bound: Bound, score: i16, best_move: Option<Move>) {
+        let index = self.index(key);
+        let generation = self.generation;
+        if let Some(old) = &self.entries[index] &&
+        old.generation == generation && old.key != key && old.depth > depth {
+            return;
+        }
+        // don't lose the old best move when this search of the same position didn't find one
+        let best_move = best_move.or_else(|| self.probe(key).and_then(|old| old.best_move));
+        self.entries[index] = Some(Entry { key, depth, bound, score, best_move, generation });
+    }
+
+    fn index(&self, key: u64) -> usize {
+        // the length is a power of two, so this is key modulo the length
+        return (key as usize) & (self.entries.len() - 1);
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        return TranspositionTable::new(DEFAULT_SIZE_MB);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Square;
+
+    fn action(from: &str, to: &str) -> Move {
+        return Move::new(Square::from_name(from).unwrap(), Square::from_name(to).unwrap());
+    }
+
+    #[test]
+    fn probe_finds_only_the_stored_position() {
+        let mut table = TranspositionTable::new(1);
+        let key = 0x1234_5678;
+        table.store(key, 3, Bound::Lower, 42, Some(action("e2", "e4")));
+
+        let entry = table.probe(key).unwrap();
+        assert_eq!((entry.depth, entry.bound, entry.score), (3, Bound::Lower, 42));
+        assert_eq!(entry.best_move, Some(action("e2", "e4")));
+
+        // a different key landing in the same slot isn't a match
+        let same_slot = key + table.entries.len() as u64;
+        assert_eq!(table.probe(same_slot), None);
+    }
+
+    #[test]
+    fn deeper_entries_survive_until_the_next_search() {
+        let mut table = TranspositionTable::new(1);
+        let key = 7;
+        let same_slot = key + table.entries.len() as u64;
+        table.store(key, 5, Bound::Exact, 10, None);
+
+        // a shallower result for another position doesn't push out a deeper one from this search
+        table.store(same_slot, 2, Bound::Exact, 20, None);
+        assert!(table.probe(key).is_some());
+
+        // but it does once that entry is left over from an earlier search
+        table.new_search();
+        table.store(same_slot, 2, Bound::Exact, 20, None);
+        assert_eq!(table.probe(key), None);
+        assert_eq!(table.probe(same_slot).unwrap().score, 20);
+    }
+
+    #[test]
+    fn storing_without_a_move_keeps_the_old_one() {
+        let mut table = TranspositionTable::new(1);
+        table.store(99, 2, Bound::Exact, 5, Some(action("g1", "f3")));
+        table.store(99, 3, Bound::Upper, -5, None);
+        let entry = table.probe(99).unwrap();
+        assert_eq!((entry.depth, entry.bound), (3, Bound::Upper));
+        assert_eq!(entry.best_move, Some(action("g1", "f3")));
+    }
+}
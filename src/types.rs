@@ -0,0 +1,276 @@
+use std::fmt;
+
+/* One of the two sides */
+#[derive(Default, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Color {
+    #[default]
+    White,
+    Black,
+}
+
+impl Color {
+    /* Returns the other side */
+    pub fn opponent(&self) -> Color {
+        return match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        };
+    }
+
+    /* Which way this side's pawns move along the ranks. White moves up the board, black moves down */
+    pub fn forward(&self) -> i8 {
+        return match self {
+            Color::White => 1,
+            Color::Black => -1,
+        };
+    }
+
+    /* Position of the side in arrays indexed by colour, 0 for white and 1 for black */
+    pub fn index(&self) -> usize {
+        return *self as usize;
+    }
+
+    /* The rank this side's pieces start on, 0 for white and 7 for black */
+    pub fn home_rank(&self) -> u8 {
+        return match self {
+            Color::White => 0,
+            Color::Black => 7,
+        };
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return match self {
+            Color::White => write!(f, "White"),
+            Color::Black => write!(f, "Black"),
+        };
+    }
+}
+
+/* The kinds of chess piece, without a colour */
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PieceKind {
+    Pawn,
+    Knight,
+    Bishop,
+    Rook,
+    Queen,
+    King,
+}
+
+impl PieceKind {
+    /* Every kind, in order of value */
+    pub const ALL: [PieceKind; 6] = [PieceKind::Pawn, PieceKind::Knight, PieceKind::Bishop,
+        PieceKind::Rook, PieceKind::Queen, PieceKind::King];
+
+    /* The pieces a pawn can promote to */
+    pub const PROMOTIONS: [PieceKind; 4] = [PieceKind::Queen, PieceKind::Rook, PieceKind::Bishop, PieceKind::Knight];
+
+    /* Position of the kind in ALL, used to index arrays of per-kind data */
+    pub fn index(&self) -> usize {
+        return *self as usize;
+    }
+
+    /* Uppercase letter used for the piece in SAN and FEN. Pawns are 'P' */
+    pub fn letter(&self) -> char {
+        return match self {
+            PieceKind::Pawn => 'P',
+            PieceKind::Knight => 'N',
+            PieceKind::Bishop => 'B',
+            PieceKind::Rook => 'R',
+            PieceKind::Queen => 'Q',
+            PieceKind::King => 'K',
+        };
+    }
+
+    /* Reads a piece letter in either case */
+    pub fn from_letter(letter: char) -> Option<PieceKind> {
+        return match letter.to_ascii_uppercase() {
+            'P' => Some(PieceKind::Pawn),
+            'N' => Some(PieceKind::Knight),
+            'B' => Some(PieceKind::Bishop),
+            'R' => Some(PieceKind::Rook),
+            'Q' => Some(PieceKind::Queen),
+            'K' => Some(PieceKind::King),
+            _ => None,
+        };
+    }
+}
+
+/* Lowercase name of the piece, like "knight" */
+impl fmt::Display for PieceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            PieceKind::Pawn => "pawn",
+            PieceKind::Knight => "knight",
+            PieceKind::Bishop => "bishop",
+            PieceKind::Rook => "rook",
+            PieceKind::Queen => "queen",
+            PieceKind::King => "king",
+        };
+        return write!(f, "{}", name);
+    }
+}
+
+/* A piece of one colour */
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Piece {
+    pub kind: PieceKind,
+    pub color: Color,
+}
+
+impl Piece {
+    pub fn new(kind: PieceKind, color: Color) -> Self {
+        return Piece { kind, color };
+    }
+
+    /* FEN letter for the piece. Uppercase for white, lowercase for black */
+    pub fn letter(&self) -> char {
+        return match self.color {
+            Color::White => self.kind.letter(),
+            Color::Black => self.kind.letter().to_ascii_lowercase(),
+        };
+    }
+
+    /* Reads a FEN letter. Uppercase letters are white */
+    pub fn from_letter(letter: char) -> Option<Piece> {
+        let color = match letter.is_ascii_uppercase() {
+            true => Color::White,
+            false => Color::Black,
+        };
+        return PieceKind::from_letter(letter).map(|kind| Piece::new(kind, color));
+    }
+
+    /* Unicode chess glyph used when printing the board */
+    pub fn glyph(&self) -> char {
+        return match (self.color, self.kind) {
+            (Color::White, PieceKind::King) => '♔',
+            (Color::White, PieceKind::Queen) => '♕',
+            (Color::White, PieceKind::Rook) => '♖',
+            (Color::White, PieceKind::Bishop) => '♗',
+            (Color::White, PieceKind::Knight) => '♘',
+            (Color::White, PieceKind::Pawn) => '♙',
+            (Color::Black, PieceKind::King) => '♚',
+            (Color::Black, PieceKind::Queen) => '♛',
+            (Color::Black, PieceKind::Rook) => '♜',
+            (Color::Black, PieceKind::Bishop) => '♝',
+            (Color::Black, PieceKind::Knight) => '♞',
+            (Color::Black, PieceKind::Pawn) => '♟',
+        };
+    }
+}
+
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}", self.glyph());
+    }
+}
+
+/*
+A square on the board, numbered 0-63 from a1, along each rank then up the board. So a1 is 0, h1 is 7,
+a2 is 8 and h8 is 63. Files and ranks are both counted from 0
+*/
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Square(u8);
+
+impl Square {
+    /* The square on file and rank, both 0-7 */
+    pub fn new(file: u8, rank: u8) -> Self {
+        return Square(rank * 8 + file);
+    }
+
+    /* The square with index 0-63 */
+    pub fn from_index(index: u8) -> Self {
+        return Square(index);
+    }
+
+    pub fn index(&self) -> u8 {
+        return self.0;
+    }
+
+    /* 0 for the a file up to 7 for the h file */
+    pub fn file(&self) -> u8 {
+        return self.0 % 8;
+    }
+
+    /* 0 for the 1st rank up to 7 for the 8th */
+    pub fn rank(&self) -> u8 {
+        return self.0 / 8;
+    }
+
+    /* The square file_step files and rank_step ranks away, or None if that's off the board */
+    pub fn offset(&self, file_step: i8, rank_step: i8) -> Option<Square> {
+        let file = self.file() as i8 + file_step;
+        let rank = self.rank() as i8 + rank_step;
+        if !(0..8).contains(&file) || !(0..8).contains(&rank) {
+            return None;
+        }
+        return Some(Square::new(file as u8, rank as u8));
+    }
+
+    /* Whether the square is a light square, used to tell bishops apart */
+    pub fn is_light(&self) -> bool {
+        return (self.file() + self.rank()) % 2 == 1;
+    }
+
+    /* Reads an algebraic square name like "e4" */
+    pub fn from_name(name: &str) -> Option<Square> {
+        let parts: Vec<char> = name.chars().collect();
+        if parts.len() != 2 || !('a'..='h').contains(&parts[0]) || !('1'..='8').contains(&parts[1]) {
+            return None;
+        }
+        return Some(Square::new(parts[0] as u8 - b'a', parts[1] as u8 - b'1'));
+    }
+
+    /* The letter of the square's file, 'a' to 'h' */
+    pub fn file_char(&self) -> char {
+        return (b'a' + self.file()) as char;
+    }
+
+    /* The digit of the square's rank, '1' to '8' */
+    pub fn rank_char(&self) -> char {
+        return (b'1' + self.rank()) as char;
+    }
+}
+
+/* Algebraic name of the square, like "e4" */
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "{}{}", self.file_char(), self.rank_char());
+    }
+}
+
+/*
+A single move from one square to another. promotion is set when a pawn reaches the last rank, holding
+the piece it becomes. Castling is a king move of two squares
+*/
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub promotion: Option<PieceKind>,
+}
+
+impl Move {
+    /* A move with no promotion */
+    pub fn new(from: Square, to: Square) -> Self {
+        return Move { from, to, promotion: None };
+    }
+
+    /* A pawn move onto the last rank, turning the pawn into kind */
+    pub fn promote(from: Square, to: Square, kind: PieceKind) -> Self {
+        return Move { from, to, promotion: Some(kind) };
+    }
+}
+
+/* Long algebraic form, as used by UCI: "e2e4", or "e7e8q" for promotions */
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(kind) = self.promotion {
+            write!(f, "{}", kind.letter().to_ascii_lowercase())?;
+        }
+        return Ok(());
+    }
+}
@@ -0,0 +1,96 @@
+use crate::state::CastlingRights;
+use crate::types::{Piece, Square};
+
+/*
+Random numbers for Zobrist hashing. A position's key is every number that applies to it XORed together:
+one for each piece on its square, one if black is to move, one for the castling rights and one for the
+file of a capturable en passant square. A move only changes a few of them, so the key can be kept up to
+date by XORing those in and out
+*/
+struct Keys {
+    // Indexed by Color::index, then PieceKind::index, then square
+    pieces: [[[u64; 64]; 6]; 2],
+    black_to_move: u64,
+    // Indexed by CastlingRights::bits
+    castling: [u64; 16],
+    // Indexed by the en passant square's file
+    en_passant: [u64; 8],
+}
+
+// Generated at compile time from a fixed seed, so keys are the same on every run
+const KEYS: Keys = generate();
+
+/* splitmix64, a small generator that gives well mixed 64 bit numbers. Returns the next seed and the number */
+const fn split_mix(seed: u64) -> (u64, u64) {
+    let seed = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = seed;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    return (seed, z ^ (z >> 31));
+}
+
+const fn generate() -> Keys {
+    let mut keys = Keys {
+        pieces: [[[0; 64]; 6]; 2],
+        black_to_move: 0,
+        castling: [0; 16],
+        en_passant: [0; 8],
+    };
+    let mut seed: u64 = 0x0DDB_1A5E_5BAD_5EED;
+    let mut number: u64;
+
+    let mut color = 0;
+    while color < 2 {
+        let mut kind = 0;
+        while kind < 6 {
+            let mut square = 0;
+            while square < 64 {
+                (seed, number) = split_mix(seed);
+                keys.pieces[color][kind][square] = number;
+                square += 1;
+            }
+            kind += 1;
+        }
+        color += 1;
+    }
+
+    (seed, number) = split_mix(seed);
+    keys.black_to_move = number;
+
+    // no castling rights at all leaves the key as it is
+    let mut rights = 1;
+    while rights < 16 {
+        (seed, number) = split_mix(seed);
+        keys.castling[rights] = number;
+        rights += 1;
+    }
+
+    let mut file = 0;
+    while file < 8 {
+        (seed, number) = split_mix(seed);
+        keys.en_passant[file] = number;
+        file += 1;
+    }
+
+    return keys;
+}
+
+/* Key for piece standing on square */
+pub fn piece(piece: Piece, square: Square) -> u64 {
+    return KEYS.pieces[piece.color.index()][piece.kind.index()][square.index() as usize];
+}
+
+/* Key XORed in when black is to move */
+pub fn black_to_move() -> u64 {
+    return KEYS.black_to_move;
+}
+
+/* Key for a set of castling rights */
+pub fn castling(rights: CastlingRights) -> u64 {
+    return KEYS.castling[rights.bits()];
+}
+
+/* Key for an en passant capture being possible onto square */
+pub fn en_passant(square: Square) -> u64 {
+    return KEYS.en_passant[square.file() as usize];
+}
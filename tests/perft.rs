@@ -0,0 +1,58 @@
+use chess_bot::fen::START_FEN;
+use chess_bot::state::State;
+
+/* Check perft from fen against the published node counts, starting at depth 1 */
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let state = State::from_fen(fen).expect("test FEN should be valid");
+    for (depth, nodes) in expected.iter().enumerate() {
+        let depth = depth as u32 + 1;
+        assert_eq!(state.perft(depth), *nodes, "perft({}) of {}", depth, fen);
+    }
+}
+
+#[test]
+fn start_position() {
+    assert_perft(START_FEN, &[20, 400, 8902, 197281]);
+}
+
+// Kiwipete, which has castling, en passant, promotions and pins all in the first few moves
+#[test]
+fn kiwipete() {
+    assert_perft("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862]);
+}
+
+// Rook and pawn endgame, full of en passant captures that would expose the king along the rank
+#[test]
+fn en_passant_endgame() {
+    assert_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238]);
+}
+
+// Promotions with capture, castling rights lost to captured rooks, and checks from the start
+#[test]
+fn promotions_and_castling() {
+    assert_perft("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467]);
+}
+
+// The same position with the colours swapped should count exactly the same
+#[test]
+fn promotions_and_castling_mirrored() {
+    assert_perft("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1", &[6, 264, 9467]);
+}
+
+#[test]
+fn underpromotion_position() {
+    assert_perft("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]);
+}
+
+#[test]
+fn middlegame_position() {
+    assert_perft("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079, 89890]);
+}
+
+#[test]
+fn divide_adds_up_to_perft() {
+    let state = State::from_fen(START_FEN).unwrap();
+    let counts = state.divide(3);
+    assert_eq!(counts.len(), 20);
+    assert_eq!(counts.iter().map(|(_, nodes)| nodes).sum::<u64>(), state.perft(3));
+}